@@ -0,0 +1,305 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not};
+
+use crate::game::{Coord, Direction};
+
+/// Number of words of a bitboard, enough for boards up to 25x25
+pub const WORDS: usize = 10;
+pub const MAX_CELLS: usize = WORDS * 64;
+
+/// One bit per cell, the index of a cell is `y * width + x` like in `Grid`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Bitboard(pub [u64; WORDS]);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard([0; WORDS]);
+
+    #[must_use]
+    pub fn single(index: usize) -> Self {
+        let mut board = Self::EMPTY;
+        board.set(index);
+        board
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, index: usize) -> bool {
+        self.0[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    #[inline]
+    pub fn unset(&mut self, index: usize) {
+        self.0[index / 64] &= !(1 << (index % 64));
+    }
+
+    #[must_use]
+    pub fn count(&self) -> u32 {
+        self.0.iter().map(|w| w.count_ones()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|w| *w == 0)
+    }
+
+    /// Move all bits to higher indices
+    #[must_use]
+    pub fn shl(&self, n: usize) -> Self {
+        let (words, bits) = (n / 64, n % 64);
+        let mut result = Self::EMPTY;
+
+        for i in words..WORDS {
+            result.0[i] = self.0[i - words] << bits;
+            if bits > 0 && i > words {
+                result.0[i] |= self.0[i - words - 1] >> (64 - bits);
+            }
+        }
+        result
+    }
+
+    /// Move all bits to lower indices
+    #[must_use]
+    pub fn shr(&self, n: usize) -> Self {
+        let (words, bits) = (n / 64, n % 64);
+        let mut result = Self::EMPTY;
+
+        for i in 0..WORDS - words.min(WORDS) {
+            result.0[i] = self.0[i + words] >> bits;
+            if bits > 0 && i + words + 1 < WORDS {
+                result.0[i] |= self.0[i + words + 1] << (64 - bits);
+            }
+        }
+        result
+    }
+
+    /// Indices of all set bits in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Self;
+
+    fn bitand(mut self, rhs: Self) -> Self {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a &= b;
+        }
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Self;
+
+    fn bitor(mut self, rhs: Self) -> Self {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a |= b;
+        }
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Self;
+
+    fn bitxor(mut self, rhs: Self) -> Self {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a ^= b;
+        }
+        self
+    }
+}
+
+/// Complement over all bits, mask with `BitGrid::board` to stay on the board
+impl Not for Bitboard {
+    type Output = Self;
+
+    fn not(mut self) -> Self {
+        for a in self.0.iter_mut() {
+            *a = !*a;
+        }
+        self
+    }
+}
+
+impl Debug for Bitboard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Shape of the board, moves whole bitboards by one cell
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BitGrid {
+    pub width: usize,
+    pub height: usize,
+    pub wrapped: bool,
+    /// all cells on the board
+    pub board: Bitboard,
+    left_col: Bitboard,
+    right_col: Bitboard,
+    bottom_row: Bitboard,
+    top_row: Bitboard,
+}
+
+impl BitGrid {
+    #[must_use]
+    pub fn new(width: usize, height: usize, wrapped: bool) -> Self {
+        assert!(width * height <= MAX_CELLS, "board too large for bitboards");
+
+        let mut grid = Self {
+            width,
+            height,
+            wrapped,
+            board: Bitboard::EMPTY,
+            left_col: Bitboard::EMPTY,
+            right_col: Bitboard::EMPTY,
+            bottom_row: Bitboard::EMPTY,
+            top_row: Bitboard::EMPTY,
+        };
+
+        for y in 0..height {
+            grid.left_col.set(y * width);
+            grid.right_col.set(y * width + width - 1);
+        }
+        for x in 0..width {
+            grid.bottom_row.set(x);
+            grid.top_row.set((height - 1) * width + x);
+        }
+        for i in 0..width * height {
+            grid.board.set(i);
+        }
+        grid
+    }
+
+    /// Whether a board of this size fits into a bitboard
+    #[must_use]
+    pub fn fits(width: usize, height: usize) -> bool {
+        width * height <= MAX_CELLS
+    }
+
+    #[must_use]
+    pub fn contains(&self, pos: Coord) -> bool {
+        self.wrapped
+            || (0 <= pos.x && pos.x < self.width as i32 && 0 <= pos.y && pos.y < self.height as i32)
+    }
+
+    /// Index of a position on the board (wraps around in wrapped games)
+    #[must_use]
+    pub fn index(&self, pos: Coord) -> usize {
+        let x = pos.x.rem_euclid(self.width as i32) as usize;
+        let y = pos.y.rem_euclid(self.height as i32) as usize;
+        y * self.width + x
+    }
+
+    #[must_use]
+    pub fn coord(&self, index: usize) -> Coord {
+        Coord {
+            x: (index % self.width) as i32,
+            y: (index / self.width) as i32,
+        }
+    }
+
+    /// Move every cell one step in the direction, cells leaving the board are dropped
+    #[must_use]
+    pub fn step(&self, cells: Bitboard, dir: Direction) -> Bitboard {
+        let (w, h) = (self.width, self.height);
+
+        let (moved, wrapped) = match dir {
+            Direction::Up => (
+                (cells & !self.top_row).shl(w),
+                (cells & self.top_row).shr(w * (h - 1)),
+            ),
+            Direction::Down => (
+                (cells & !self.bottom_row).shr(w),
+                (cells & self.bottom_row).shl(w * (h - 1)),
+            ),
+            Direction::Right => (
+                (cells & !self.right_col).shl(1),
+                (cells & self.right_col).shr(w - 1),
+            ),
+            Direction::Left => (
+                (cells & !self.left_col).shr(1),
+                (cells & self.left_col).shl(w - 1),
+            ),
+            Direction::None => return cells,
+        };
+
+        if self.wrapped {
+            moved | wrapped
+        } else {
+            moved
+        }
+    }
+
+    /// All cells next to the given cells
+    #[must_use]
+    pub fn neighbours(&self, cells: Bitboard) -> Bitboard {
+        Direction::get_alive_actions()
+            .into_iter()
+            .fold(Bitboard::EMPTY, |acc, dir| acc | self.step(cells, dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_carry_across_words() {
+        let board = Bitboard::single(63);
+
+        assert_eq!(board.shl(1).iter().collect::<Vec<_>>(), vec![64]);
+        assert_eq!(board.shl(130).iter().collect::<Vec<_>>(), vec![193]);
+        assert_eq!(board.shl(1).shr(1), board);
+        assert!(board.shr(64).is_empty());
+    }
+
+    #[test]
+    fn steps_drop_or_wrap_at_the_edges() {
+        let grid = BitGrid::new(11, 11, false);
+        let corner = Bitboard::single(grid.index(Coord { x: 10, y: 10 }));
+
+        assert!(grid.step(corner, Direction::Up).is_empty());
+        assert!(grid.step(corner, Direction::Right).is_empty());
+        assert_eq!(grid.neighbours(corner).count(), 2);
+
+        let wrapped = BitGrid::new(11, 11, true);
+        let up = wrapped.step(corner, Direction::Up);
+        assert_eq!(up, Bitboard::single(wrapped.index(Coord { x: 10, y: 0 })));
+        let right = wrapped.step(corner, Direction::Right);
+        assert_eq!(
+            right,
+            Bitboard::single(wrapped.index(Coord { x: 0, y: 10 }))
+        );
+        assert_eq!(wrapped.neighbours(corner).count(), 4);
+    }
+}
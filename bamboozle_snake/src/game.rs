@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::ops::{Add, Sub};
 
 #[macro_export]
@@ -32,8 +32,83 @@ pub struct Game {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Ruleset {
     pub name: String,
-    // pub version: String,
-    // pub settings: HashMap<String, Value>,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub settings: Settings,
+}
+
+/// Settings of the ruleset, missing values fall back to the engine defaults
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    pub food_spawn_chance: u32,
+    pub minimum_food: u32,
+    #[serde(deserialize_with = "null_as_default_hazard_damage")]
+    pub hazard_damage_per_turn: i16,
+    pub royale: RoyaleSettings,
+    pub squad: SquadSettings,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RoyaleSettings {
+    pub shrink_every_n_turns: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SquadSettings {
+    pub allow_body_collisions: bool,
+    pub shared_elimination: bool,
+    pub shared_health: bool,
+    pub shared_length: bool,
+}
+
+const DEFAULT_HAZARD_DAMAGE: i16 = 14;
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            food_spawn_chance: 15,
+            minimum_food: 1,
+            hazard_damage_per_turn: DEFAULT_HAZARD_DAMAGE,
+            royale: RoyaleSettings::default(),
+            squad: SquadSettings::default(),
+        }
+    }
+}
+
+impl Default for RoyaleSettings {
+    fn default() -> Self {
+        Self {
+            shrink_every_n_turns: 25,
+        }
+    }
+}
+
+impl Settings {
+    /// Weight of an owned hazard cell compared to a free one.
+    /// `discount` is the weight at the default damage, every further
+    /// `DEFAULT_HAZARD_DAMAGE` applies it again. Harmless hazards are worth a full cell,
+    /// lethal ones nothing.
+    #[must_use]
+    pub fn hazard_weight(&self, discount: f32) -> f32 {
+        if self.hazard_damage_per_turn <= 0 {
+            1.0
+        } else if self.hazard_damage_per_turn >= 100 {
+            0.0
+        } else {
+            discount.powf(self.hazard_damage_per_turn as f32 / DEFAULT_HAZARD_DAMAGE as f32)
+        }
+    }
+}
+
+fn null_as_default_hazard_damage<'de, D>(deserializer: D) -> Result<i16, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<i16>::deserialize(deserializer)?.unwrap_or(DEFAULT_HAZARD_DAMAGE))
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -55,6 +130,8 @@ pub struct Battlesnake {
     // pub length: u32,
     pub latency: Option<String>,
     pub shout: Option<String>,
+    #[serde(default)]
+    pub squad: String,
 }
 
 impl PartialEq for Battlesnake {
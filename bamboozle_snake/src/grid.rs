@@ -28,6 +28,15 @@ impl<T: Default + Copy> Grid<T> {
         }
     }
 
+    /// Resize to a board and fill every cell with `value`, keeps the allocation
+    pub fn reset(&mut self, width: usize, height: usize, wrapped: bool, value: T) {
+        self.width = width;
+        self.height = height;
+        self.wrapped = wrapped;
+        self.cells.clear();
+        self.cells.resize(width * height, value);
+    }
+
     #[must_use]
     pub fn contains(&self, pos: Coord) -> bool {
         if self.wrapped {
@@ -74,6 +83,18 @@ impl<T: Default + Copy> Grid<T> {
     }
 }
 
+/// Empty grid without cells
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            wrapped: false,
+            cells: Vec::new(),
+        }
+    }
+}
+
 impl<T> Grid<T> {
     pub fn wrap_around(&self, coord: &mut Coord) {
         let width = self.width as i32;
@@ -0,0 +1,145 @@
+use crate::bitboard::Bitboard;
+use crate::simulation::{BitState, MAX_SNAKES};
+
+/// Floodfill on bitboards, all cells of a distance are expanded at once.
+/// Gives the same areas as `FloodType::Simple`
+#[derive(Clone, Debug)]
+pub struct BitFloodfill {
+    /// cells each snake reaches first
+    pub owned: [Bitboard; MAX_SNAKES],
+    /// cells reached at the same time by snakes of the same length
+    pub draw: Bitboard,
+    snakes: usize,
+}
+
+impl BitFloodfill {
+    #[must_use]
+    pub fn new(bits: &BitState) -> Self {
+        let snakes = &bits.state.snakes;
+        let grid = &bits.grid;
+
+        let mut ordered_ids = [0; MAX_SNAKES];
+        let mut alive = 0;
+        for i in (0..snakes.len()).filter(|i| snakes[*i].is_alive()) {
+            ordered_ids[alive] = i;
+            alive += 1;
+        }
+        let ordered_ids = &mut ordered_ids[..alive];
+        ordered_ids.sort_by(|&i, &j| snakes[j].len().cmp(&snakes[i].len()));
+
+        // like the simple floodfill whole bodies block, tails included
+        let bodies = bits
+            .bodies
+            .iter()
+            .fold(Bitboard::EMPTY, |acc, body| acc | *body);
+
+        let mut owned = [Bitboard::EMPTY; MAX_SNAKES];
+        let mut frontiers = [Bitboard::EMPTY; MAX_SNAKES];
+        for id in ordered_ids.iter() {
+            frontiers[*id] = Bitboard::single(bits.heads[*id]);
+        }
+
+        let mut draw = Bitboard::EMPTY;
+        let mut free = grid.board & !bodies;
+
+        loop {
+            let mut claimed = Bitboard::EMPTY;
+
+            // longer snakes win contested cells, equal lengths draw
+            for group in ordered_ids.chunk_by(|&i, &j| snakes[i].len() == snakes[j].len()) {
+                let mut reached = Bitboard::EMPTY;
+                let mut reached_twice = Bitboard::EMPTY;
+
+                for id in group {
+                    let new = grid.neighbours(frontiers[*id]) & free & !claimed;
+                    reached_twice |= reached & new;
+                    reached |= new;
+                    frontiers[*id] = new;
+                }
+
+                for id in group {
+                    frontiers[*id] &= !reached_twice;
+                    owned[*id] |= frontiers[*id];
+                }
+                draw |= reached_twice;
+                claimed |= reached;
+            }
+
+            if claimed.is_empty() {
+                break;
+            }
+            free &= !claimed;
+        }
+
+        Self {
+            owned,
+            draw,
+            snakes: snakes.len(),
+        }
+    }
+
+    /// Owned cells of every snake, like `Floodfill::count_owned_all`
+    #[must_use]
+    pub fn count_owned_all(&self) -> Vec<i32> {
+        self.owned[..self.snakes]
+            .iter()
+            .map(|o| o.count() as i32)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic::floodfill::FloodType;
+    use crate::heuristic::{CellFlood, Floodfill};
+    use crate::simulation::State;
+    use crate::test_util::{load_games, random_actions, with_rules, RULESETS};
+
+    /// Owned cells and draws of the queue based flood as bitboards
+    fn flood_bits(floodfill: &Floodfill) -> (Vec<Bitboard>, Bitboard) {
+        let mut owned = vec![Bitboard::EMPTY; floodfill.state.snakes.len()];
+        let mut draw = Bitboard::EMPTY;
+
+        for (i, cell) in floodfill.cells.cells.iter().enumerate() {
+            match cell {
+                CellFlood::Owned { id, .. } => owned[*id as usize].set(i),
+                CellFlood::Draw => draw.set(i),
+                _ => (),
+            }
+        }
+        (owned, draw)
+    }
+
+    #[test]
+    fn areas_match_the_simple_flood() {
+        let mut rng = rand::thread_rng();
+
+        for game in load_games() {
+            for (ruleset, map) in RULESETS {
+                for _ in 0..10 {
+                    let mut state = State::from(&with_rules(&game, ruleset, map));
+
+                    while !state.is_end_state() && state.turn < game.turn + 100 {
+                        let bits = state.bits();
+                        let bit_flood = BitFloodfill::new(&bits);
+                        let floodfill = Floodfill::new(&state, FloodType::Simple);
+
+                        let (owned, draw) = flood_bits(&floodfill);
+                        let context = format!("{ruleset} {map} turn {}", state.turn);
+                        assert_eq!(&bit_flood.owned[..owned.len()], &owned[..], "{context}");
+                        assert_eq!(bit_flood.draw, draw, "{context}");
+                        assert_eq!(
+                            bit_flood.count_owned_all(),
+                            floodfill.count_owned_all(),
+                            "{context}"
+                        );
+
+                        let actions = random_actions(&state, &mut rng);
+                        state = state.step(&actions);
+                    }
+                }
+            }
+        }
+    }
+}
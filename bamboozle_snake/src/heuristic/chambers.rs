@@ -0,0 +1,340 @@
+use crate::game::Coord;
+use crate::grid::Grid;
+
+/// Articulation points and chambers of the region reachable from a cell.
+///
+/// A chamber is a biconnected part of the region, chambers are connected by articulation
+/// points. A snake that passes an articulation point can not come back, so it can fill its
+/// own chamber and then only the best chamber behind one of the exits (tree of chambers).
+#[derive(Debug, Clone, Default)]
+pub struct Chambers {
+    /// cells reachable from the start, without the start
+    pub reachable: usize,
+    /// cells that can be visited in one go, without the start
+    pub fillable: usize,
+    /// fillable cells per checkerboard color `(x + y) % 2`
+    pub fillable_colors: [usize; 2],
+    /// color of the start, `None` if the board is no checkerboard (wrapped with odd sides)
+    pub start_color: Option<usize>,
+    /// cells whose removal splits the region
+    pub articulation_points: Vec<Coord>,
+    /// both cells of every crossing of a wrap seam that the region only crosses narrowly
+    pub seam_chokepoints: Vec<Coord>,
+    pub chambers: usize,
+}
+
+/// Widest crossing of a wrap seam that still counts as chokepoint
+const SEAM_CHOKEPOINT_WIDTH: usize = 2;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DfsCell {
+    /// discovery time, 0 if not visited
+    disc: u16,
+    low: u16,
+    /// cells of the subtree that are in the chamber of this cell, per color
+    own: [u16; 2],
+    /// best chamber tree behind an exit of the subtree, per color
+    exit: [u16; 2],
+}
+
+/// Buffers of the depth first search, reused by `Chambers::analyse_in`
+#[derive(Debug, Clone, Default)]
+pub struct ChamberBuffers {
+    cells: Grid<DfsCell>,
+    stack: Vec<(Coord, usize)>,
+}
+
+fn color(pos: Coord) -> usize {
+    (pos.x + pos.y).rem_euclid(2) as usize
+}
+
+/// Cells of a single color, the other color is empty
+fn single(color: usize) -> [u16; 2] {
+    let mut cells = [0; 2];
+    cells[color] = 1;
+    cells
+}
+
+fn add(a: [u16; 2], b: [u16; 2]) -> [u16; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+/// The larger of two chamber trees
+fn larger(a: [u16; 2], b: [u16; 2]) -> [u16; 2] {
+    if b[0] + b[1] > a[0] + a[1] {
+        b
+    } else {
+        a
+    }
+}
+
+/// Crossings of the left/right and the bottom/top seam whose both cells are in the region,
+/// a seam crossed by only a few of them is a passage the snake can be cut off at
+fn seam_chokepoints(cells: &Grid<DfsCell>, chokepoints: &mut Vec<Coord>) {
+    let (width, height) = (cells.width as i32, cells.height as i32);
+
+    let vertical = (0..height).map(|y| (Coord { x: width - 1, y }, Coord { x: 0, y }));
+    add_narrow_crossings(cells, vertical, chokepoints);
+    let horizontal = (0..width).map(|x| (Coord { x, y: height - 1 }, Coord { x, y: 0 }));
+    add_narrow_crossings(cells, horizontal, chokepoints);
+}
+
+fn add_narrow_crossings(
+    cells: &Grid<DfsCell>,
+    seam: impl Iterator<Item = (Coord, Coord)> + Clone,
+    chokepoints: &mut Vec<Coord>,
+) {
+    let crossings = seam.filter(|(a, b)| cells[*a].disc != 0 && cells[*b].disc != 0);
+
+    if crossings.clone().count() <= SEAM_CHOKEPOINT_WIDTH {
+        chokepoints.extend(crossings.flat_map(|(a, b)| [a, b]));
+    }
+}
+
+impl Chambers {
+    /// Analyse the cells reachable from `start` over passable cells, `start` is part of the
+    /// region even if it is not passable
+    #[must_use]
+    pub fn new<T: Default + Copy>(
+        grid: &Grid<T>,
+        start: Coord,
+        passable: impl Fn(&T) -> bool,
+    ) -> Self {
+        let mut chambers = Chambers::default();
+        chambers.analyse_in(grid, start, passable, &mut ChamberBuffers::default());
+        chambers
+    }
+
+    /// Like `new`, but overwrites this analysis and searches in the buffers of an earlier
+    /// one, nothing is allocated once the buffers fit the board
+    pub fn analyse_in<T: Default + Copy>(
+        &mut self,
+        grid: &Grid<T>,
+        start: Coord,
+        passable: impl Fn(&T) -> bool,
+        buffers: &mut ChamberBuffers,
+    ) {
+        let ChamberBuffers { cells, stack } = buffers;
+        cells.reset(grid.width, grid.height, grid.wrapped, DfsCell::default());
+        stack.clear();
+
+        self.chambers = 0;
+        self.articulation_points.clear();
+        self.seam_chokepoints.clear();
+
+        let mut time = 1;
+        cells[start] = DfsCell {
+            disc: time,
+            low: time,
+            own: single(color(start)),
+            exit: [0; 2],
+        };
+        let mut root_children = 0;
+
+        // iterative dfs, every frame holds the cell and its next neighbour
+        stack.push((start, 0));
+        while let Some((pos, next)) = stack.last_mut() {
+            let pos = *pos;
+
+            if *next < 4 {
+                let mut neighbour = pos.get_neighbours()[*next];
+                *next += 1;
+                if grid.wrapped {
+                    grid.wrap_around(&mut neighbour);
+                }
+
+                if !grid.contains(neighbour) || (neighbour != start && !passable(&grid[neighbour]))
+                {
+                    continue;
+                }
+
+                if cells[neighbour].disc == 0 {
+                    time += 1;
+                    cells[neighbour] = DfsCell {
+                        disc: time,
+                        low: time,
+                        own: single(color(neighbour)),
+                        exit: [0; 2],
+                    };
+                    stack.push((neighbour, 0));
+                } else {
+                    cells[pos].low = cells[pos].low.min(cells[neighbour].disc);
+                }
+                continue;
+            }
+
+            // all neighbours are done, merge the subtree into the parent
+            stack.pop();
+            let Some((parent, _)) = stack.last() else {
+                break;
+            };
+            let (parent, child) = (*parent, cells[pos]);
+            let is_root = stack.len() == 1;
+
+            let parent_cell = &mut cells[parent];
+            parent_cell.low = parent_cell.low.min(child.low);
+
+            if child.low >= parent_cell.disc {
+                // the subtree is only reachable through the parent
+                self.chambers += 1;
+                parent_cell.exit = larger(parent_cell.exit, add(child.own, child.exit));
+
+                if is_root {
+                    root_children += 1;
+                }
+                if (!is_root || root_children == 2) && !self.articulation_points.contains(&parent) {
+                    self.articulation_points.push(parent);
+                }
+            } else {
+                parent_cell.own = add(parent_cell.own, child.own);
+                parent_cell.exit = larger(parent_cell.exit, child.exit);
+            }
+        }
+
+        let root = cells[start];
+        let mut fillable = add(root.own, root.exit);
+        fillable[color(start)] -= 1;
+
+        self.reachable = time as usize - 1;
+        self.fillable = (fillable[0] + fillable[1]) as usize;
+        self.fillable_colors = fillable.map(|c| c as usize);
+
+        let checkerboard =
+            !grid.wrapped || (grid.width.is_multiple_of(2) && grid.height.is_multiple_of(2));
+        self.start_color = checkerboard.then(|| color(start));
+
+        if grid.wrapped {
+            seam_chokepoints(cells, &mut self.seam_chokepoints);
+        }
+    }
+
+    /// Estimate of the longest path from the start. Every move changes the color of the
+    /// checkerboard, so a path uses at most one more fillable cell of the other color than
+    /// of the color of the start
+    #[must_use]
+    pub fn max_path(&self) -> usize {
+        let Some(start_color) = self.start_color else {
+            return self.fillable;
+        };
+
+        let same = self.fillable_colors[start_color];
+        let other = self.fillable_colors[1 - start_color];
+        self.fillable
+            .min(2 * same.min(other) + usize::from(other > same))
+    }
+
+    /// Share of the reachable cells that can be filled
+    #[must_use]
+    pub fn fill_ratio(&self) -> f32 {
+        if self.reachable == 0 {
+            0.0
+        } else {
+            self.fillable as f32 / self.reachable as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid of passable cells, `#` is a wall. The first row of the picture is the top
+    fn grid(rows: &[&str], wrapped: bool) -> Grid<bool> {
+        let mut grid = Grid::new(rows[0].len(), rows.len(), wrapped);
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                grid[Coord {
+                    x: x as i32,
+                    y: y as i32,
+                }] = c != '#';
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn corridor_fills_one_side() {
+        let grid = grid(&["....."], false);
+        let chambers = Chambers::new(&grid, Coord { x: 2, y: 0 }, |c| *c);
+
+        assert_eq!(chambers.reachable, 4);
+        assert_eq!(chambers.fillable, 2);
+        assert_eq!(chambers.chambers, 4);
+        assert_eq!(chambers.articulation_points.len(), 3);
+    }
+
+    #[test]
+    fn door_between_two_rooms() {
+        let grid = grid(&["...#...", ".......", "...#..."], false);
+
+        // from inside a room the other room is filled after the own one
+        let chambers = Chambers::new(&grid, Coord { x: 0, y: 1 }, |c| *c);
+        assert_eq!(chambers.reachable, 18);
+        assert_eq!(chambers.fillable, 18);
+        assert_eq!(chambers.chambers, 4);
+
+        // from the door only one of the rooms
+        let chambers = Chambers::new(&grid, Coord { x: 3, y: 1 }, |c| *c);
+        assert_eq!(chambers.reachable, 18);
+        assert_eq!(chambers.fillable, 9);
+        assert_eq!(chambers.fill_ratio(), 0.5);
+        assert_eq!(chambers.fillable_colors, [5, 4]);
+
+        let mut points = chambers.articulation_points;
+        points.sort_by_key(|p| p.x);
+        assert_eq!(
+            points,
+            vec![
+                Coord { x: 2, y: 1 },
+                Coord { x: 3, y: 1 },
+                Coord { x: 4, y: 1 }
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_corridor_is_a_ring() {
+        let start = Coord { x: 0, y: 0 };
+
+        let chambers = Chambers::new(&grid(&["....."], true), start, |c| *c);
+        assert_eq!(chambers.reachable, 4);
+        assert_eq!(chambers.fillable, 4);
+        assert_eq!(chambers.chambers, 1);
+        assert!(chambers.articulation_points.is_empty());
+
+        let chambers = Chambers::new(&grid(&["....."], false), start, |c| *c);
+        assert_eq!(chambers.articulation_points.len(), 3);
+    }
+
+    #[test]
+    fn max_path_counts_colors_of_the_filled_room() {
+        // both rooms together have enough cells of each color, the filled one does not:
+        // the room is entered on its smaller color, so a cell of the larger one is left
+        let rooms = grid(&["...#...", ".......", "...#..."], false);
+        let chambers = Chambers::new(&rooms, Coord { x: 3, y: 1 }, |c| *c);
+        assert_eq!(chambers.start_color, Some(0));
+        assert_eq!(chambers.max_path(), 8);
+
+        // a ring of odd length is no checkerboard
+        let chambers = Chambers::new(&grid(&["....."], true), Coord { x: 0, y: 0 }, |c| *c);
+        assert_eq!(chambers.start_color, None);
+        assert_eq!(chambers.max_path(), 4);
+    }
+
+    #[test]
+    fn narrow_seam_crossing_is_a_chokepoint() {
+        // the middle row crosses the left/right seam, four columns the bottom/top seam
+        let rows = ["#....#", "......", "#....#"];
+        let start = Coord { x: 2, y: 1 };
+
+        let chambers = Chambers::new(&grid(&rows, true), start, |c| *c);
+        assert!(chambers.articulation_points.is_empty());
+        assert_eq!(
+            chambers.seam_chokepoints,
+            vec![Coord { x: 5, y: 1 }, Coord { x: 0, y: 1 }]
+        );
+
+        let chambers = Chambers::new(&grid(&rows, false), start, |c| *c);
+        assert!(chambers.seam_chokepoints.is_empty());
+    }
+}
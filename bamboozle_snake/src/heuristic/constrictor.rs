@@ -1,14 +1,20 @@
 use crate::heuristic::floodfill::FloodType;
-use crate::heuristic::{Floodfill, Heuristic, StandardHeuristic};
-use crate::simulation::{Outcome, State};
+use crate::heuristic::{BitFloodfill, Features, Floodfill, Heuristic, StandardHeuristic};
+use crate::simulation::{BitState, Outcome, State, MAX_SNAKES};
 
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+/// Names of the terms, in the order of the weights and the features
+const FEATURES: &[&str] = &["alive_enemies", "area", "path", "sealed"];
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ConstrictorHeuristic {
     pub area: f32,
     pub alive_enemies: f32,
+    pub path: f32,
+    pub sealed: f32,
 }
 
 impl Default for ConstrictorHeuristic {
@@ -16,6 +22,8 @@ impl Default for ConstrictorHeuristic {
         Self {
             area: 0.01,
             alive_enemies: 0.1,
+            path: 0.03,
+            sealed: 0.3,
         }
     }
 }
@@ -37,28 +45,159 @@ impl ToString for ConstrictorHeuristic {
 impl Heuristic for ConstrictorHeuristic {
     fn eval(&self, state: &State) -> Outcome {
         if !state.snakes[0].is_alive() {
-            return Outcome::Loss(state.snakes[0].loss_reason);
+            return Outcome::Loss(state.snakes[0].loss_reason());
         }
 
+        let areas = ConstrictorHeuristic::areas(state);
+        let features = Floodfill::with(state, self.flood_type(state), |floodfill| {
+            let paths = ConstrictorHeuristic::max_paths(floodfill);
+            ConstrictorHeuristic::snake_features(floodfill, &areas, &paths, 0)
+        });
+        Outcome::Heuristic(features.dot(&self.weights()))
+    }
+
+    fn weights(&self) -> Features {
+        Features::new(
+            FEATURES,
+            &[self.alive_enemies, self.area, self.path, self.sealed],
+        )
+    }
+
+    fn features(&self, state: &State) -> Vec<Option<Features>> {
+        let areas = ConstrictorHeuristic::areas(state);
+        Floodfill::with(state, self.flood_type(state), |floodfill| {
+            let paths = ConstrictorHeuristic::max_paths(floodfill);
 
-        let alive_enemies_score = StandardHeuristic::alive_enemies(state, 0);
+            state
+                .snakes
+                .iter()
+                .enumerate()
+                .map(|(snake_id, snake)| {
+                    snake.is_alive().then(|| {
+                        ConstrictorHeuristic::snake_features(floodfill, &areas, &paths, snake_id)
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn flood_type(&self, _state: &State) -> FloodType {
+        FloodType::Constrictor
+    }
+}
 
-        let floodfill = Floodfill::new(state, FloodType::Simple);
-        let owned_areas = floodfill.count_owned_all();
+impl ConstrictorHeuristic {
+    /// Every move fills a cell for good, so the snake that can move longer wins. A snake
+    /// whose area is cut off from all others can not be disturbed anymore, then the
+    /// difference of the paths decides the game.
+    pub fn snake_features(
+        floodfill: &Floodfill,
+        owned_areas: &[i32],
+        paths: &[usize],
+        snake_id: usize,
+    ) -> Features {
+        let state = floodfill.state;
+        let alive_enemies_score = StandardHeuristic::alive_enemies(state, snake_id);
+
+        let enemies =
+            || (0..state.snakes.len()).filter(|i| *i != snake_id && state.snakes[*i].is_alive());
+
+        let max_enemy_area = enemies().map(|i| owned_areas[i]).max().unwrap_or(-1);
+        let area_score = (owned_areas[snake_id] - max_enemy_area) as f32;
+
+        let max_enemy_path = enemies().map(|i| paths[i] as f32).max_by(f32::total_cmp);
+        let path_score = paths[snake_id] as f32 - max_enemy_path.unwrap_or(0.0);
+
+        let sealed_score = enemies()
+            .filter(|i| floodfill.dead_ends[snake_id] || floodfill.dead_ends[*i])
+            .map(|i| paths[snake_id] as f32 - paths[i] as f32)
+            .sum();
+
+        Features::new(
+            FEATURES,
+            &[alive_enemies_score, area_score, path_score, sealed_score],
+        )
+    }
+
+    /// Cells every snake reaches first, bodies block like in `FloodType::Simple`. Runs on
+    /// bitboards unless the board is too large for them
+    fn areas(state: &State) -> [i32; MAX_SNAKES] {
+        if BitState::supports(state) {
+            return BitFloodfill::new(&state.bits())
+                .owned
+                .map(|owned| owned.count() as i32);
+        }
 
-        let mut max_enemy_area = -1;
-        for i in 1..state.snakes.len() {
-            if state.snakes[i].is_alive() && owned_areas[i] > max_enemy_area {
-                max_enemy_area = owned_areas[i];
+        let mut areas = [0; MAX_SNAKES];
+        let owned = Floodfill::new(state, FloodType::Simple).count_owned_all();
+        areas[..owned.len()].copy_from_slice(&owned);
+        areas
+    }
+
+    /// Longest path every snake can still move in its area, 0 for eliminated snakes
+    fn max_paths(floodfill: &Floodfill) -> [usize; MAX_SNAKES] {
+        let mut paths = [0; MAX_SNAKES];
+        for (i, snake) in floodfill.state.snakes.iter().enumerate() {
+            if snake.is_alive() {
+                paths[i] = ConstrictorHeuristic::max_path(floodfill, i as u8);
             }
         }
-        
-        let area_score = (owned_areas[0] - max_enemy_area) as f32;
-        let score = self.alive_enemies * alive_enemies_score + self.area * area_score;
-        Outcome::Heuristic(score)
+        paths
+    }
+
+    /// Estimate of the longest path: behind an articulation point only the best chamber
+    /// can be filled, and the colors of the checkerboard alternate along the path
+    fn max_path(floodfill: &Floodfill, snake_id: u8) -> usize {
+        floodfill.chambers(snake_id).max_path()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Battlesnake;
+    use crate::test_util::{game, snake, with_rules};
+
+    /// Constrictor game on a 7x7 board
+    fn constrictor(snakes: Vec<Battlesnake>) -> State {
+        let mut game = with_rules(&game(snakes), "constrictor", "standard");
+        (game.board.width, game.board.height) = (7, 7);
+        State::from(&game)
     }
 
-    fn eval_all(&self, state: &State) -> Vec<Outcome> {
-        todo!()
+    /// Features of snake 0, checked against the evaluation
+    fn features(state: &State) -> Features {
+        let heuristic = ConstrictorHeuristic::default();
+        let features = heuristic.features(state)[0].unwrap();
+
+        let Outcome::Heuristic(score) = heuristic.eval(state) else {
+            panic!("snake 0 is alive");
+        };
+        assert_eq!(score, features.dot(&heuristic.weights()));
+        features
+    }
+
+    #[test]
+    fn sealed_areas_compare_the_paths() {
+        // our body walls off the left three columns, the enemy keeps the right ones
+        let wall = [(2, 6)].into_iter().chain((0..7).rev().map(|y| (3, y)));
+        let you = snake("you", wall, 100);
+        let enemy = snake("enemy", [(5, 3), (5, 2), (5, 1)], 100);
+        let features = features(&constrictor(vec![you, enemy]));
+
+        // 20 free cells on our side against 18 around the enemy, both can be filled
+        assert_eq!(features.get("path"), Some(2.0));
+        assert_eq!(features.get("sealed"), Some(2.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn open_areas_are_not_sealed() {
+        let you = snake("you", [(1, 3), (1, 2), (1, 1)], 100);
+        let enemy = snake("enemy", [(5, 3), (5, 2), (5, 1)], 100);
+        let features = features(&constrictor(vec![you, enemy]));
+
+        // the same room on both sides of the contested middle column
+        assert_eq!(features.get("path"), Some(0.0));
+        assert_eq!(features.get("sealed"), Some(0.0));
+    }
+}
@@ -1,16 +1,27 @@
-use crate::heuristic::floodfill::FloodType;
-use crate::heuristic::{Floodfill, Heuristic, StandardHeuristic};
+use crate::heuristic::{
+    duel_enemy, Chambers, Features, Floodfill, FoodRace, Heuristic, StandardHeuristic,
+};
 use crate::simulation::{Outcome, Snake, State};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+/// Names of the terms, in the order of the weights and the features
+const FEATURES: &[&str] = &[
+    "health", "area", "dead_end", "length", "food", "space", "survival", "open",
+];
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DuelsHeuristic {
     pub area: f32,
     pub snake_area: f32,
     pub health: f32,
     pub length: f32,
     pub food: f32,
+    pub space: f32,
+    pub survival: f32,
+    /// Distance to the chokepoints of the own area, only on wrapped boards
+    pub open: f32,
 }
 
 impl Default for DuelsHeuristic {
@@ -21,6 +32,9 @@ impl Default for DuelsHeuristic {
             health: 0.05,
             length: 0.0,
             food: 0.0,
+            space: 0.0,
+            survival: 0.0,
+            open: 0.05,
         }
     }
 }
@@ -42,51 +56,114 @@ impl ToString for DuelsHeuristic {
 impl Heuristic for DuelsHeuristic {
     fn eval(&self, state: &State) -> Outcome {
         if !state.snakes[0].is_alive() {
-            return Outcome::Loss(state.snakes[0].loss_reason);
+            return Outcome::Loss(state.snakes[0].loss_reason());
         }
-        let floodfill = Floodfill::new(state, FloodType::FollowSnakes);
+        let features = Floodfill::with(state, self.flood_type(state), |floodfill| {
+            self.snake_features(state, floodfill, 0)
+        });
+        Outcome::Heuristic(features.dot(&self.weights()))
+    }
+
+    fn weights(&self) -> Features {
+        Features::new(
+            FEATURES,
+            &[
+                self.health,
+                self.area,
+                self.area,
+                self.length,
+                self.food,
+                self.space,
+                self.survival,
+                self.open,
+            ],
+        )
+    }
 
-        let our_snake = &state.snakes[0];
-        let (enemy_id, enemy_snake) = state
-            .snakes
-            .iter()
-            .enumerate()
-            .skip(1)
-            .find(|(_, s)| s.is_alive())
-            .unwrap();
+    fn features(&self, state: &State) -> Vec<Option<Features>> {
+        Floodfill::with(state, self.flood_type(state), |floodfill| {
+            state
+                .snakes
+                .iter()
+                .enumerate()
+                .map(|(snake_id, snake)| {
+                    snake
+                        .is_alive()
+                        .then(|| self.snake_features(state, floodfill, snake_id))
+                })
+                .collect()
+        })
+    }
+}
+
+impl DuelsHeuristic {
+    pub fn snake_features(
+        &self,
+        state: &State,
+        floodfill: &Floodfill,
+        snake_id: usize,
+    ) -> Features {
+        let our_snake = &state.snakes[snake_id];
+        let enemy_id = duel_enemy(state, snake_id);
+        let enemy_snake = &state.snakes[enemy_id];
 
         let health_score = self.health(our_snake);
         let length_score = self.length(our_snake, enemy_snake);
-        let food_score = StandardHeuristic::food(state, &floodfill, 0);
+        // the races are not cached, skip them if food does not count
+        let food_score = if self.food == 0.0 {
+            0.0
+        } else {
+            FoodRace::score(&floodfill.food_races(), floodfill, snake_id)
+        };
+        // the chambers are the most expensive term, compute them once and only if they count
+        let open = state.grid.wrapped && self.open != 0.0;
+        let chambers = (self.space != 0.0 || open).then(|| floodfill.chambers(snake_id as u8));
+        let space_score = if self.space == 0.0 {
+            0.0
+        } else {
+            chambers.as_deref().map_or(0.0, Chambers::fill_ratio)
+        };
+        let open_score = match &chambers {
+            Some(chambers) if open => StandardHeuristic::open(state, chambers, snake_id),
+            _ => 0.0,
+        };
+        let survival_score = if self.survival == 0.0 {
+            0.0
+        } else {
+            floodfill.survival(snake_id).score(our_snake.len())
+        };
 
         // area score
-        let (our_cells, our_snake_cells, enemy_cells, enemy_snake_cells) = floodfill.count_duels();
+        let (our_cells, our_snake_cells, enemy_cells, enemy_snake_cells) =
+            floodfill.count_duels(snake_id as u8);
 
         let our_cell_sum = our_cells as f32 + self.snake_area * our_snake_cells as f32;
         let enemy_cell_sum = enemy_cells as f32 + self.snake_area * enemy_snake_cells as f32;
 
-        let mut area_score = our_cell_sum / (our_cell_sum + enemy_cell_sum);
-
-        if floodfill.dead_ends[0] || floodfill.dead_ends[1] {
-            let area_diff =
-                (our_cells + our_snake_cells) as f32 - (enemy_cells + enemy_snake_cells) as f32;
-            area_score += area_diff;
-        }
-
-        let score = self.health * health_score
-            + self.area * area_score
-            + self.length * length_score
-            + self.food * food_score;
-
-        Outcome::Heuristic(score)
+        let area_score = our_cell_sum / (our_cell_sum + enemy_cell_sum);
+
+        // a dead end decides the game, the larger area wins
+        let dead_end_score = if floodfill.dead_ends[snake_id] || floodfill.dead_ends[enemy_id] {
+            (our_cells + our_snake_cells) as f32 - (enemy_cells + enemy_snake_cells) as f32
+        } else {
+            0.0
+        };
+
+        Features::new(
+            FEATURES,
+            &[
+                health_score,
+                area_score,
+                dead_end_score,
+                length_score,
+                food_score,
+                space_score,
+                survival_score,
+                open_score,
+            ],
+        )
     }
 
-    fn eval_all(&self, state: &State) -> Vec<Outcome> {
-        todo!()
-    }
-}
-
-impl DuelsHeuristic {
     fn length(&self, our_snake: &Snake, enemy_snake: &Snake) -> f32 {
         our_snake.len() as f32 / (our_snake.len() as f32 + enemy_snake.len() as f32)
     }
@@ -0,0 +1,68 @@
+/// Most terms a heuristic can have
+pub const MAX_FEATURES: usize = 12;
+
+/// Raw terms of a heuristic (or the weights of these terms), stored inline.
+/// The names are a const slice of the heuristic, values are in the same order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Features {
+    names: &'static [&'static str],
+    values: [f32; MAX_FEATURES],
+}
+
+impl Features {
+    #[must_use]
+    pub fn new(names: &'static [&'static str], values: &[f32]) -> Self {
+        assert!(names.len() <= MAX_FEATURES, "too many features");
+        debug_assert_eq!(names.len(), values.len(), "{names:?}");
+
+        let mut features = Self {
+            names,
+            values: [0.0; MAX_FEATURES],
+        };
+        features.values[..values.len()].copy_from_slice(values);
+        features
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.names
+            .iter()
+            .position(|n| *n == name)
+            .map(|i| self.values[i])
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.names.iter().copied()
+    }
+
+    #[must_use]
+    pub fn values(&self) -> &[f32] {
+        &self.values[..self.names.len()]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, f32)> + '_ {
+        self.names().zip(self.values().iter().copied())
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Weighted sum of the features, both have to use the same names
+    #[must_use]
+    pub fn dot(&self, weights: &Features) -> f32 {
+        debug_assert_eq!(self.names, weights.names);
+
+        self.values()
+            .iter()
+            .zip(weights.values())
+            .map(|(v, w)| w * v)
+            .sum()
+    }
+}
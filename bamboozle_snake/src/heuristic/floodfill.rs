@@ -1,4 +1,5 @@
 use crate::game::Coord;
+use std::cell::{Ref, RefCell};
 use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::fmt::{Debug, Formatter};
@@ -6,7 +7,8 @@ use std::iter::zip;
 use yansi::Paint;
 
 use crate::grid::Grid;
-use crate::simulation::{Snake, State};
+use crate::heuristic::{ChamberBuffers, Chambers, FoodRace, RaceBuffers};
+use crate::simulation::{Growth, RulesConfig, Snake, State, Survival, SurvivalBuffers};
 
 #[derive(PartialEq, Clone, Copy, Default)]
 pub enum CellFlood {
@@ -57,6 +59,8 @@ pub struct FloodElement {
     health: i16,
     length: u8,
     food_eaten: u8,
+    /// health spent to get here
+    cost: u32,
 }
 
 impl FloodElement {
@@ -71,6 +75,7 @@ impl FloodElement {
             health: self.health - 1,
             length: self.length,
             food_eaten: self.food_eaten,
+            cost: self.cost + 1,
         })
     }
 }
@@ -80,6 +85,47 @@ pub enum FloodType {
     Simple,
     FollowSnakes,
     Constrictor,
+    /// Follows the snakes and expands by spent health, so hazards slow a snake down.
+    /// Only used if a heuristic opts in, hazards already cost their damage here
+    HazardCost,
+}
+
+impl FloodType {
+    /// Flood that follows the snakes the way the rules move them
+    #[must_use]
+    pub fn for_rules(config: &RulesConfig) -> Self {
+        match config.growth {
+            Growth::Constrictor => FloodType::Constrictor,
+            Growth::Standard => FloodType::FollowSnakes,
+        }
+    }
+}
+
+/// Buffers of a floodfill, the next flood resets them in place instead of allocating new ones
+#[derive(Clone, Debug, Default)]
+pub struct FloodWorkspace {
+    cells: Grid<CellFlood>,
+    dead_ends: Vec<bool>,
+    queue: VecDeque<FloodElement>,
+    costs: Grid<u32>,
+    buckets: Vec<Vec<FloodElement>>,
+    ordered_ids: Vec<usize>,
+    analyses: Analyses,
+}
+
+/// Results and buffers of the analyses on top of a flood, each borrowed by one analysis at
+/// a time. A result is kept until the same analysis runs again
+#[derive(Clone, Debug, Default)]
+struct Analyses {
+    chambers: RefCell<Chambers>,
+    chamber_buffers: RefCell<ChamberBuffers>,
+    survival: RefCell<SurvivalBuffers>,
+    races: RefCell<RaceBuffers>,
+}
+
+thread_local! {
+    /// workspace of the search thread, shared by all floods of its leaf evaluations
+    static WORKSPACE: RefCell<FloodWorkspace> = RefCell::default();
 }
 
 #[derive(Clone, Debug)]
@@ -87,26 +133,97 @@ pub struct Floodfill<'a> {
     pub state: &'a State,
     pub cells: Grid<CellFlood>,
     pub dead_ends: Vec<bool>,
+    queue: VecDeque<FloodElement>,
+    costs: Grid<u32>,
+    buckets: Vec<Vec<FloodElement>>,
+    /// alive snakes, longest first
+    ordered_ids: Vec<usize>,
+    analyses: Analyses,
 }
 
 impl<'a> Floodfill<'a> {
     pub fn new(state: &'a State, flood_type: FloodType) -> Self {
+        Floodfill::new_in(state, flood_type, FloodWorkspace::default())
+    }
+
+    /// Flood in the buffers of `workspace`, they only grow if the board is larger than before
+    pub fn new_in(state: &'a State, flood_type: FloodType, workspace: FloodWorkspace) -> Self {
+        let FloodWorkspace {
+            mut cells,
+            mut dead_ends,
+            mut queue,
+            costs,
+            buckets,
+            ordered_ids,
+            analyses,
+        } = workspace;
+
+        let (width, height, wrapped) = (state.grid.width, state.grid.height, state.grid.wrapped);
+        if cells.width == width && cells.height == height {
+            cells.wrapped = wrapped;
+            cells.clear();
+        } else {
+            cells = Grid::new(width, height, wrapped);
+        }
+        dead_ends.clear();
+        dead_ends.resize(state.snakes.len(), true);
+        queue.clear();
+
         let mut floodfill = Self {
             state,
-            cells: Grid::new(state.grid.width, state.grid.height, state.grid.wrapped),
-            dead_ends: vec![true; state.snakes.len()],
+            cells,
+            dead_ends,
+            queue,
+            costs,
+            buckets,
+            ordered_ids,
+            analyses,
         };
         floodfill.fill_snakes(&state.snakes);
+        floodfill.order_ids();
 
         match flood_type {
             FloodType::Simple => floodfill.calc_simple(),
             FloodType::FollowSnakes => floodfill.calc_follow_snakes(),
             FloodType::Constrictor => floodfill.calc_constrictor(),
+            FloodType::HazardCost => floodfill.calc_hazard_cost(),
         }
 
         floodfill
     }
 
+    /// Flood in the workspace of the current thread, the floodfill only lives during `f`
+    pub fn with<R>(state: &'a State, flood_type: FloodType, f: impl FnOnce(&Self) -> R) -> R {
+        let workspace = WORKSPACE.with(|cell| cell.take());
+        let floodfill = Floodfill::new_in(state, flood_type, workspace);
+
+        let result = f(&floodfill);
+
+        let workspace = floodfill.into_workspace();
+        WORKSPACE.with(|cell| cell.replace(workspace));
+        result
+    }
+
+    /// Give back the buffers for the next flood
+    #[must_use]
+    pub fn into_workspace(self) -> FloodWorkspace {
+        FloodWorkspace {
+            cells: self.cells,
+            dead_ends: self.dead_ends,
+            queue: self.queue,
+            costs: self.costs,
+            buckets: self.buckets,
+            ordered_ids: self.ordered_ids,
+            analyses: self.analyses,
+        }
+    }
+
+    /// Flood that follows the snakes the way the rules of the state move them
+    #[must_use]
+    pub fn for_rules(state: &'a State) -> Self {
+        Floodfill::new(state, FloodType::for_rules(&state.config))
+    }
+
     pub fn clear(&mut self) {
         self.cells.clear();
     }
@@ -114,7 +231,7 @@ impl<'a> Floodfill<'a> {
     /// Fill grid with snakes
     fn fill_snakes(&mut self, snakes: &[Snake]) {
         for (id, snake) in snakes.iter().enumerate().filter(|(_, s)| s.is_alive()) {
-            for (tail_dist, body_part) in snake.body.iter().rev().enumerate() {
+            for (tail_dist, body_part) in snake.body().rev().enumerate() {
                 self.cells[*body_part] = CellFlood::Snake {
                     id: id as u8,
                     tail_dist: tail_dist as u8,
@@ -123,24 +240,25 @@ impl<'a> Floodfill<'a> {
         }
     }
 
-    fn get_ordered_ids(&self, snakes: &Vec<Snake>) -> Vec<usize> {
-        let mut ordered_ids: Vec<usize> = (0..snakes.len())
-            .filter(|&i| snakes[i].is_alive())
-            .collect();
-        ordered_ids.sort_by(|&i, &j| snakes[j].len().cmp(&snakes[i].len()));
+    /// Order the alive snake ids by length, in the buffer of the workspace
+    fn order_ids(&mut self) {
+        let snakes = &self.state.snakes;
 
-        ordered_ids
+        self.ordered_ids.clear();
+        self.ordered_ids
+            .extend((0..snakes.len()).filter(|&i| snakes[i].is_alive()));
+        self.ordered_ids
+            .sort_by(|&i, &j| snakes[j].len().cmp(&snakes[i].len()));
     }
 
     fn calc_simple(&mut self) {
         let snakes = &self.state.snakes;
-        let ordered_ids = self.get_ordered_ids(snakes);
 
-        let mut elem_queue =
-            VecDeque::with_capacity(self.cells.width * self.cells.height * snakes.len());
+        let mut elem_queue = std::mem::take(&mut self.queue);
+        elem_queue.reserve(self.cells.width * self.cells.height * snakes.len());
 
         // init queue with heads
-        for id in ordered_ids.iter() {
+        for id in self.ordered_ids.iter() {
             let snake = &snakes[*id];
             elem_queue.push_back(FloodElement {
                 id: *id as u8,
@@ -149,6 +267,7 @@ impl<'a> Floodfill<'a> {
                 health: snake.health,
                 length: snake.len() as u8,
                 food_eaten: 0,
+                cost: 0,
             });
         }
 
@@ -185,6 +304,7 @@ impl<'a> Floodfill<'a> {
                 }
             }
         }
+        self.queue = elem_queue;
     }
 
     fn calc_follow_snakes(&mut self) {
@@ -192,13 +312,12 @@ impl<'a> Floodfill<'a> {
         let snakes = &self.state.snakes;
 
         // order snake-ids by length
-        let ordered_ids = self.get_ordered_ids(snakes);
 
-        let mut elem_queue =
-            VecDeque::with_capacity(self.cells.width * self.cells.height * snakes.len());
+        let mut elem_queue = std::mem::take(&mut self.queue);
+        elem_queue.reserve(self.cells.width * self.cells.height * snakes.len());
 
         // init queue with heads
-        for id in ordered_ids.iter() {
+        for id in self.ordered_ids.iter() {
             let snake = &snakes[*id];
             elem_queue.push_back(FloodElement {
                 id: *id as u8,
@@ -207,6 +326,7 @@ impl<'a> Floodfill<'a> {
                 health: snake.health,
                 length: snake.len() as u8,
                 food_eaten: 0,
+                cost: 0,
             });
         }
 
@@ -241,7 +361,8 @@ impl<'a> Floodfill<'a> {
                     continue;
                 }
 
-                neighbour.health -= self.state.grid[neighbour.pos].hazard as i16 * 14;
+                neighbour.health -= self.state.grid[neighbour.pos].hazard as i16
+                    * self.state.config.settings.hazard_damage_per_turn;
 
                 let cell = &mut self.cells[neighbour.pos];
 
@@ -317,6 +438,147 @@ impl<'a> Floodfill<'a> {
                 }
             }
         }
+        self.queue = elem_queue;
+    }
+
+    /// Like `calc_follow_snakes`, but a bucket queue expands the cells in order of spent health.
+    /// A cell belongs to the snake that reaches it with the least health lost, hazards count
+    /// their damage and cells a snake can not reach alive stay free.
+    fn calc_hazard_cost(&mut self) {
+        let snakes = &self.state.snakes;
+        let damage = self.state.config.settings.hazard_damage_per_turn.max(0);
+
+        // lowest cost of every cell, draws keep the cost of the tie
+        let mut costs = std::mem::take(&mut self.costs);
+        if costs.width != self.cells.width || costs.height != self.cells.height {
+            costs = Grid::new(self.cells.width, self.cells.height, self.cells.wrapped);
+        }
+        costs.wrapped = self.cells.wrapped;
+        costs.cells.fill(u32::MAX);
+
+        // buckets of an earlier flood are empty, but keep their allocations
+        let mut buckets = std::mem::take(&mut self.buckets);
+        if buckets.is_empty() {
+            buckets.push(Vec::new());
+        }
+        for id in self.ordered_ids.iter() {
+            let snake = &snakes[*id];
+            buckets[0].push(FloodElement {
+                id: *id as u8,
+                step: 0,
+                pos: snake.head(),
+                health: snake.health,
+                length: snake.len() as u8,
+                food_eaten: 0,
+                cost: 0,
+            });
+        }
+
+        let mut current = 0;
+        while current < buckets.len() {
+            // elements of the current cost, new ones always cost more
+            let mut bucket = std::mem::take(&mut buckets[current]);
+            current += 1;
+
+            for mut elem in bucket.drain(..) {
+                match self.cells[elem.pos] {
+                    CellFlood::Draw => {
+                        self.dead_ends[elem.id as usize] = false;
+                        continue;
+                    }
+                    CellFlood::Owned { id, .. } if id != elem.id => {
+                        self.dead_ends[elem.id as usize] = false;
+                        continue;
+                    }
+                    // a cheaper way to the cell was already expanded
+                    CellFlood::Owned { .. } if costs[elem.pos] < elem.cost => continue,
+                    _ => (),
+                }
+
+                // check if eaten or starved
+                if self.state.grid.is_food(elem.pos) {
+                    elem.health = 100;
+                    elem.length += 1;
+                    elem.food_eaten += 1;
+                } else if elem.health <= 0 {
+                    continue;
+                }
+
+                for neighbour in elem.get_neighbours().iter_mut() {
+                    if !self.cells.contains(neighbour.pos) {
+                        continue;
+                    }
+
+                    let hazard_damage = self.state.grid[neighbour.pos].hazard as i16 * damage;
+                    neighbour.health -= hazard_damage;
+                    neighbour.cost += hazard_damage as u32;
+
+                    // the snake would not survive entering the cell
+                    if neighbour.health <= 0 && !self.state.grid.is_food(neighbour.pos) {
+                        continue;
+                    }
+
+                    let claim = match self.cells[neighbour.pos] {
+                        CellFlood::Free => true,
+                        CellFlood::Snake { id, tail_dist } if id == neighbour.id => {
+                            ((tail_dist + neighbour.food_eaten) as u32) < neighbour.step
+                        }
+                        CellFlood::Snake { tail_dist, .. } => (tail_dist as u32) < neighbour.step,
+                        CellFlood::Owned { id, length, .. } => {
+                            // must be connected to another snakes area
+                            if id != elem.id {
+                                self.dead_ends[elem.id as usize] = false;
+                            }
+
+                            match neighbour.cost.cmp(&costs[neighbour.pos]) {
+                                Ordering::Less => true,
+                                Ordering::Equal if id != neighbour.id => {
+                                    match neighbour.length.cmp(&length) {
+                                        Ordering::Equal => {
+                                            self.cells[neighbour.pos] = CellFlood::Draw;
+                                            false
+                                        }
+                                        Ordering::Greater => true,
+                                        Ordering::Less => false,
+                                    }
+                                }
+                                _ => false,
+                            }
+                        }
+                        CellFlood::Draw => {
+                            self.dead_ends[elem.id as usize] = false;
+                            neighbour.cost < costs[neighbour.pos]
+                        }
+                    };
+
+                    if claim {
+                        let was_snake = match self.cells[neighbour.pos] {
+                            CellFlood::Snake { .. } => true,
+                            CellFlood::Owned { was_snake, .. } => was_snake,
+                            _ => false,
+                        };
+                        self.cells[neighbour.pos] = CellFlood::Owned {
+                            id: neighbour.id,
+                            step: neighbour.step,
+                            length: neighbour.length,
+                            health: neighbour.health as _,
+                            was_snake,
+                        };
+                        costs[neighbour.pos] = neighbour.cost;
+
+                        let cost = neighbour.cost as usize;
+                        if buckets.len() <= cost {
+                            buckets.resize_with(cost + 1, Vec::new);
+                        }
+                        buckets[cost].push(*neighbour);
+                    }
+                }
+            }
+            buckets[current - 1] = bucket;
+        }
+
+        self.costs = costs;
+        self.buckets = buckets;
     }
 
     fn calc_constrictor(&mut self) {
@@ -324,13 +586,12 @@ impl<'a> Floodfill<'a> {
         let snakes = &self.state.snakes;
 
         // order snake-ids by length
-        let ordered_ids = self.get_ordered_ids(snakes);
 
-        let mut elem_queue =
-            VecDeque::with_capacity(self.cells.width * self.cells.height * snakes.len());
+        let mut elem_queue = std::mem::take(&mut self.queue);
+        elem_queue.reserve(self.cells.width * self.cells.height * snakes.len());
 
         // init queue with heads
-        for id in ordered_ids.iter() {
+        for id in self.ordered_ids.iter() {
             let snake = &snakes[*id];
             elem_queue.push_back(FloodElement {
                 id: *id as u8,
@@ -339,6 +600,7 @@ impl<'a> Floodfill<'a> {
                 health: 100,
                 length: snake.len() as u8,
                 food_eaten: 0,
+                cost: 0,
             });
         }
 
@@ -401,10 +663,12 @@ impl<'a> Floodfill<'a> {
                 }
             }
         }
+        self.queue = elem_queue;
     }
 
+    /// Cells of the snake and of all other snakes combined
     #[must_use]
-    pub fn count_duels(&self) -> (usize, usize, usize, usize) {
+    pub fn count_duels(&self, snake_id: u8) -> (usize, usize, usize, usize) {
         let mut our_cells = 0;
         let mut our_s_cells = 0;
 
@@ -413,7 +677,7 @@ impl<'a> Floodfill<'a> {
 
         for cell in self.cells.cells.iter() {
             if let CellFlood::Owned { id, was_snake, .. } = cell {
-                if *id == 0 {
+                if *id == snake_id {
                     if *was_snake {
                         our_s_cells += 1;
                     } else {
@@ -450,6 +714,35 @@ impl<'a> Floodfill<'a> {
         (owned, owned_snake)
     }
 
+    /// Chambers of the area the snake owns, seen from its head. Analysed in the workspace,
+    /// so the chambers of the last call have to be dropped first
+    #[must_use]
+    pub fn chambers(&self, snake_id: u8) -> Ref<'_, Chambers> {
+        let head = self.state.snakes[snake_id as usize].head();
+
+        self.analyses.chambers.borrow_mut().analyse_in(
+            &self.cells,
+            head,
+            |cell| matches!(cell, CellFlood::Owned { id, .. } if *id == snake_id),
+            &mut self.analyses.chamber_buffers.borrow_mut(),
+        );
+        self.analyses.chambers.borrow()
+    }
+
+    /// `State::survival` of the snake in the buffers of the flood
+    #[must_use]
+    pub fn survival(&self, snake_id: usize) -> Survival {
+        self.state
+            .survival_in(snake_id, &mut self.analyses.survival.borrow_mut())
+    }
+
+    /// `FoodRace::all` in the workspace, the races of the last call have to be dropped first
+    #[must_use]
+    pub fn food_races(&self) -> Ref<'_, [FoodRace]> {
+        FoodRace::all_in(self.state, &mut self.analyses.races.borrow_mut());
+        Ref::map(self.analyses.races.borrow(), RaceBuffers::races)
+    }
+
     #[must_use]
     pub fn count_owned_all(&self) -> Vec<i32> {
         let mut owned = vec![0; self.state.snakes.len()];
@@ -468,19 +761,28 @@ impl<'a> Floodfill<'a> {
         let mut owned_snakes = 0;
         let mut owned_snake_hazards = 0;
 
-        for (flood_cell, grid_cell) in zip(self.cells.cells.iter(), self.state.grid.cells.iter()) {
+        for (i, (flood_cell, grid_cell)) in
+            zip(self.cells.cells.iter(), self.state.grid.cells.iter()).enumerate()
+        {
             if let CellFlood::Owned { id, was_snake, .. } = flood_cell {
                 if *id != snake_id {
                     continue;
                 }
 
+                // cells that are about to burn count as hazards
+                let pos = Coord {
+                    x: (i % self.cells.width) as i32,
+                    y: (i / self.cells.width) as i32,
+                };
+                let is_hazard = grid_cell.hazard > 0 || self.state.is_possible_hazard(pos);
+
                 if *was_snake {
-                    if grid_cell.hazard > 0 {
+                    if is_hazard {
                         owned_snake_hazards += 1;
                     } else {
                         owned_snakes += 1;
                     }
-                } else if grid_cell.hazard > 0 {
+                } else if is_hazard {
                     owned_hazards += 1;
                 } else {
                     owned += 1;
@@ -491,3 +793,72 @@ impl<'a> Floodfill<'a> {
         (owned, owned_hazards, owned_snakes, owned_snake_hazards)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{game, load_games, snake, with_rules};
+
+    #[test]
+    fn hazards_slow_down_the_hazard_cost_flood() {
+        let mut game = game(vec![
+            snake("you", [(3, 5), (2, 5), (1, 5)], 100),
+            snake("enemy", [(9, 5), (10, 5), (10, 6)], 100),
+        ]);
+        // a wall of hazards between us and the contested cell
+        game.board.hazards = (0..11).map(|y| Coord { x: 4, y }).collect();
+
+        let state = State::from(&game);
+        let contested = Coord { x: 5, y: 5 };
+
+        // 2 cells away through the hazard against 4 cells away on clean ground
+        let floodfill = Floodfill::new(&state, FloodType::HazardCost);
+        assert!(matches!(
+            floodfill.cells[contested],
+            CellFlood::Owned { id: 1, .. }
+        ));
+
+        let floodfill = Floodfill::new(&state, FloodType::FollowSnakes);
+        assert!(matches!(
+            floodfill.cells[contested],
+            CellFlood::Owned { id: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn analyses_in_the_workspace_match_fresh_ones() {
+        let states: Vec<State> = load_games()
+            .iter()
+            .flat_map(|game| {
+                ["standard", "wrapped"].map(|rules| with_rules(game, rules, "standard"))
+            })
+            .map(|game| State::from(&game))
+            .collect();
+
+        // twice, so every analysis runs in buffers of another board
+        for state in states.iter().chain(states.iter()) {
+            Floodfill::with(state, FloodType::for_rules(&state.config), |floodfill| {
+                for (i, snake) in state
+                    .snakes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| s.is_alive())
+                {
+                    let fresh = Chambers::new(
+                        &floodfill.cells,
+                        snake.head(),
+                        |cell| matches!(cell, CellFlood::Owned { id, .. } if *id as usize == i),
+                    );
+                    let chambers = floodfill.chambers(i as u8);
+                    assert_eq!(chambers.reachable, fresh.reachable);
+                    assert_eq!(chambers.fillable_colors, fresh.fillable_colors);
+                    assert_eq!(chambers.articulation_points, fresh.articulation_points);
+                    assert_eq!(chambers.seam_chokepoints, fresh.seam_chokepoints);
+
+                    assert_eq!(floodfill.survival(i), state.survival(i));
+                }
+                assert_eq!(*floodfill.food_races(), FoodRace::all(state)[..]);
+            });
+        }
+    }
+}
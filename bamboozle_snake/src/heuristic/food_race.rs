@@ -0,0 +1,285 @@
+use crate::game::Coord;
+use crate::grid::Grid;
+use crate::heuristic::{Floodfill, StandardHeuristic};
+use crate::simulation::State;
+use std::collections::VecDeque;
+
+/// Earliest arrival of every snake at a food and who gets it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FoodRace {
+    pub food: Coord,
+    /// turns until the snake reaches the food, None if it can not get there alive
+    pub arrivals: Vec<Option<u16>>,
+    /// length of the snake when it arrives, with the food it eats on the way
+    pub lengths: Vec<u16>,
+    /// first snake to reach the food, the longest wins a tie. None if nobody gets there or
+    /// the first snakes have the same length and collide head-on
+    pub winner: Option<u8>,
+}
+
+impl FoodRace {
+    /// Races for every food on the board
+    #[must_use]
+    pub fn all(state: &State) -> Vec<FoodRace> {
+        let mut buffers = RaceBuffers::default();
+        FoodRace::all_in(state, &mut buffers);
+        buffers.races
+    }
+
+    /// Like `all`, but searches and keeps the races in the buffers of an earlier call, the
+    /// races of the last call are overwritten in place
+    pub fn all_in<'b>(state: &State, buffers: &'b mut RaceBuffers) -> &'b [FoodRace] {
+        let snakes = state.snakes.len();
+        buffers
+            .races
+            .resize_with(state.food.len(), FoodRace::default);
+        if state.food.is_empty() {
+            return &buffers.races;
+        }
+
+        let alive = |i: usize| state.snakes[i].is_alive();
+        buffers.arrivals.resize_with(snakes, Arrivals::default);
+        for i in (0..snakes).filter(|i| alive(*i)) {
+            arrival_times(state, i, buffers);
+        }
+        let grids = &buffers.arrivals[..snakes];
+
+        for (race, food) in buffers.races.iter_mut().zip(&state.food) {
+            race.food = *food;
+            race.arrivals.clear();
+            race.arrivals.extend(
+                (0..snakes)
+                    .map(|i| Some(grids[i].turns[*food]).filter(|t| alive(i) && *t != u16::MAX)),
+            );
+            race.lengths.clear();
+            race.lengths.extend((0..snakes).map(|i| {
+                if alive(i) {
+                    grids[i].lengths[*food]
+                } else {
+                    0
+                }
+            }));
+            race.winner = race_winner(&race.arrivals, &race.lengths);
+        }
+        &buffers.races
+    }
+
+    /// Turns until the snake eats the food, if it wins the race
+    #[must_use]
+    pub fn won_by(&self, snake_id: usize) -> Option<u16> {
+        match self.winner {
+            Some(winner) if winner as usize == snake_id => self.arrivals[snake_id],
+            _ => None,
+        }
+    }
+
+    /// `StandardHeuristic::food` over the foods the snake wins instead of the foods in its area
+    #[must_use]
+    pub fn score(races: &[FoodRace], floodmap: &Floodfill, snake_id: usize) -> f32 {
+        let food_dists = races
+            .iter()
+            .filter_map(|race| race.won_by(snake_id))
+            .map(|turns| turns as u32);
+
+        StandardHeuristic::food_score(floodmap.state, floodmap, snake_id, food_dists)
+    }
+}
+
+/// The first snake wins, on a tie the longest snake, equal lengths collide
+fn race_winner(arrivals: &[Option<u16>], lengths: &[u16]) -> Option<u8> {
+    let first = arrivals.iter().flatten().min()?;
+
+    let mut winner = None;
+    let mut winner_len = 0;
+    let mut tie = false;
+    for (i, _) in arrivals
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| **a == Some(*first))
+    {
+        let len = lengths[i];
+        if len > winner_len {
+            winner = Some(i as u8);
+            winner_len = len;
+            tie = false;
+        } else if len == winner_len {
+            tie = true;
+        }
+    }
+
+    if tie {
+        None
+    } else {
+        winner
+    }
+}
+
+/// Most times a cell is expanded again, later visits only count with more health or food
+const MAX_VISITS: u8 = 100;
+
+/// Earliest turn a snake can be on every cell and its length then
+#[derive(Debug, Clone, Default)]
+struct Arrivals {
+    /// `u16::MAX` if the snake can not get there alive
+    turns: Grid<u16>,
+    /// longest length at the earliest turn, food on the cell itself not yet eaten
+    lengths: Grid<u16>,
+}
+
+/// Grids and results of the races, reused by `FoodRace::all_in`
+#[derive(Debug, Clone, Default)]
+pub struct RaceBuffers {
+    races: Vec<FoodRace>,
+    /// arrivals of every snake, stale for dead snakes
+    arrivals: Vec<Arrivals>,
+    free_at: Grid<u16>,
+    owner: Grid<u8>,
+    /// best health and food a cell was reached with, a longer path is only worth it with more
+    best_health: Grid<i16>,
+    best_eaten: Grid<u16>,
+    visits: Grid<u8>,
+    queue: VecDeque<(Coord, u16, i16, u16)>,
+}
+
+impl RaceBuffers {
+    /// Races of the last `FoodRace::all_in`
+    #[must_use]
+    pub fn races(&self) -> &[FoodRace] {
+        &self.races
+    }
+}
+
+/// Fill in the arrivals of the snake. Bodies free up once their tail has passed, other
+/// snakes might eat once so their bodies need one turn of margin like in
+/// `State::survival_from`. Hazards and starvation cost health, food on the way makes the
+/// snake longer.
+fn arrival_times(state: &State, snake_i: usize, buffers: &mut RaceBuffers) {
+    let RaceBuffers {
+        races: _,
+        arrivals,
+        free_at,
+        owner,
+        best_health,
+        best_eaten,
+        visits,
+        queue,
+    } = buffers;
+    let arrivals = &mut arrivals[snake_i];
+
+    state.body_timing(snake_i, free_at, owner);
+    let damage = state.config.settings.hazard_damage_per_turn;
+    let snake = &state.snakes[snake_i];
+
+    let (width, height, wrapped) = (state.grid.width, state.grid.height, state.grid.wrapped);
+    arrivals.turns.reset(width, height, wrapped, u16::MAX);
+    arrivals.lengths.reset(width, height, wrapped, 0);
+    best_health.reset(width, height, wrapped, 0);
+    best_eaten.reset(width, height, wrapped, 0);
+    visits.reset(width, height, wrapped, 0);
+
+    let length = snake.len() as u16;
+    arrivals.turns[snake.head()] = 0;
+    arrivals.lengths[snake.head()] = length;
+    best_health[snake.head()] = snake.health;
+
+    queue.clear();
+    queue.push_back((snake.head(), 0, snake.health, 0));
+
+    while let Some((pos, step, health, eaten)) = queue.pop_front() {
+        for mut next in pos.get_neighbours() {
+            if !state.grid.contains(next) {
+                continue;
+            }
+            if state.grid.wrapped {
+                state.grid.wrap_around(&mut next);
+            }
+
+            let step: u16 = step + 1;
+            if free_at[next] > 0 {
+                let margin = if owner[next] as usize == snake_i {
+                    0
+                } else {
+                    1
+                };
+                if step < free_at[next] + margin {
+                    continue;
+                }
+            }
+
+            let is_food = state.grid.is_food(next);
+            let health = if is_food {
+                100
+            } else {
+                health - 1 - state.grid[next].hazard as i16 * damage
+            };
+            if health <= 0 {
+                continue;
+            }
+            if health <= best_health[next] && eaten <= best_eaten[next] {
+                continue;
+            }
+            if visits[next] >= MAX_VISITS {
+                continue;
+            }
+            visits[next] += 1;
+
+            if step < arrivals.turns[next] {
+                arrivals.turns[next] = step;
+                arrivals.lengths[next] = length + eaten;
+            } else if step == arrivals.turns[next] {
+                arrivals.lengths[next] = arrivals.lengths[next].max(length + eaten);
+            }
+            best_health[next] = best_health[next].max(health);
+            best_eaten[next] = best_eaten[next].max(eaten);
+
+            queue.push_back((next, step, health, eaten + u16::from(is_food)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{game, snake};
+
+    /// Two snakes of length 3 race from both sides of the middle row for the centre
+    fn races(left_health: u32, food: &[(i32, i32)]) -> Vec<FoodRace> {
+        let mut game = game(vec![
+            snake("left", [(2, 5), (1, 5), (0, 5)], left_health),
+            snake("right", [(8, 5), (9, 5), (10, 5)], 100),
+        ]);
+        game.board.food = food.iter().map(|(x, y)| Coord { x: *x, y: *y }).collect();
+
+        FoodRace::all(&State::from(&game))
+    }
+
+    #[test]
+    fn equal_lengths_collide() {
+        let races = races(100, &[(5, 5)]);
+
+        assert_eq!(races[0].arrivals, vec![Some(3), Some(3)]);
+        assert_eq!(races[0].lengths, vec![3, 3]);
+        assert_eq!(races[0].winner, None);
+    }
+
+    #[test]
+    fn food_on_the_way_wins_the_tie() {
+        let races = races(100, &[(5, 5), (3, 5)]);
+
+        assert_eq!(races[0].arrivals, vec![Some(3), Some(3)]);
+        assert_eq!(races[0].lengths, vec![4, 3]);
+        assert_eq!(races[0].won_by(0), Some(3));
+        assert_eq!(races[1].won_by(0), Some(1));
+    }
+
+    #[test]
+    fn starving_snake_does_not_arrive() {
+        // eating on the last bit of health is in time
+        assert_eq!(races(3, &[(5, 5)])[0].arrivals, vec![Some(3), Some(3)]);
+
+        let races = races(2, &[(5, 5)]);
+
+        assert_eq!(races[0].arrivals, vec![None, Some(3)]);
+        assert_eq!(races[0].won_by(1), Some(3));
+    }
+}
@@ -1,22 +1,105 @@
+mod bit_floodfill;
+mod chambers;
 mod constrictor;
 mod duels;
+mod features;
 mod floodfill;
+mod food_race;
+mod profile;
 mod royale;
 mod royale_duels;
+mod solo;
 mod standard;
 
 use crate::simulation::{Outcome, State};
+pub use bit_floodfill::BitFloodfill;
+pub use chambers::{ChamberBuffers, Chambers};
 pub use constrictor::ConstrictorHeuristic;
 pub use duels::DuelsHeuristic;
-pub use floodfill::{CellFlood, Floodfill};
+pub use features::Features;
+pub use floodfill::{CellFlood, FloodType, FloodWorkspace, Floodfill};
+pub use food_race::{FoodRace, RaceBuffers};
+pub use profile::{HeuristicProfile, Profile, ProfileMode, ProfileRegistry};
 pub use royale::RoyaleHeuristic;
 pub use royale_duels::RoyaleDuelsHeuristic;
+pub use solo::SoloHeuristic;
 pub use standard::StandardHeuristic;
 use std::fmt::Debug;
+use std::iter::zip;
 
 pub trait Heuristic: Debug + Send + Sync {
     /// Evaluate the current state and return an outcome
     /// Trait for all different types of heuristics
     fn eval(&self, state: &State) -> Outcome;
-    fn eval_all(&self, state: &State) -> Vec<Outcome>;
+
+    /// Weights of the features, the score is their weighted sum
+    fn weights(&self) -> Features;
+
+    /// Raw features of every snake from its own perspective, `None` for eliminated snakes
+    fn features(&self, state: &State) -> Vec<Option<Features>>;
+
+    /// Flood the features are computed on
+    fn flood_type(&self, state: &State) -> FloodType {
+        FloodType::for_rules(&state.config)
+    }
+
+    fn eval_all(&self, state: &State) -> Vec<Outcome> {
+        let weights = self.weights();
+
+        zip(&state.snakes, self.features(state))
+            .map(|(snake, features)| match features {
+                Some(features) => Outcome::Heuristic(features.dot(&weights)),
+                None => Outcome::Loss(snake.loss_reason()),
+            })
+            .collect()
+    }
+}
+
+/// Opponent of a snake in a duel or its area rival in royale, the first other snake that is
+/// still alive
+pub(crate) fn duel_enemy(state: &State, snake_id: usize) -> usize {
+    let mut others = (0..state.snakes.len()).filter(|i| *i != snake_id);
+
+    others
+        .clone()
+        .find(|i| state.snakes[*i].is_alive())
+        .or_else(|| others.next())
+        .unwrap_or(snake_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{load_games, with_rules};
+
+    /// The `open` feature of snake 0 in a duel, on the board of the ruleset and on a wrapped one
+    fn open(heuristic: &dyn Heuristic, ruleset: &str, solo: bool) -> [f32; 2] {
+        let game = &load_games()[1];
+
+        [ruleset, "wrapped"].map(|ruleset| {
+            let mut game = with_rules(game, ruleset, "standard");
+            if solo {
+                game.board.snakes.retain(|s| s.id == game.you.id);
+            }
+
+            let state = State::from(&game);
+            heuristic.features(&state)[0].unwrap().get("open").unwrap()
+        })
+    }
+
+    #[test]
+    fn open_only_counts_on_wrapped_boards() {
+        let duels = DuelsHeuristic::default();
+        let royale_duels = RoyaleDuelsHeuristic::default();
+        let solo = SoloHeuristic::default();
+
+        for [standard, wrapped] in [
+            open(&duels, "standard", false),
+            open(&royale_duels, "royale", false),
+            open(&solo, "solo", true),
+        ] {
+            assert_eq!(standard, 0.0);
+            assert!(wrapped > 0.0 && wrapped <= 1.0);
+        }
+    }
 }
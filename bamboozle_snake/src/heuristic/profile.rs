@@ -0,0 +1,362 @@
+use crate::heuristic::{
+    ConstrictorHeuristic, DuelsHeuristic, Heuristic, RoyaleDuelsHeuristic, RoyaleHeuristic,
+    SoloHeuristic, StandardHeuristic,
+};
+use crate::simulation::{Growth, HazardSource, RulesConfig};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Player counts the profiles have to cover
+const MAX_PLAYERS: usize = 8;
+
+/// Built in routing, used without a profile file
+const DEFAULT_PROFILES: &str = include_str!("../../profiles/default.json");
+
+/// Game mode a profile applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileMode {
+    Standard,
+    Royale,
+    Snail,
+    Constrictor,
+    Solo,
+}
+
+impl ProfileMode {
+    pub const ALL: [ProfileMode; 5] = [
+        ProfileMode::Standard,
+        ProfileMode::Royale,
+        ProfileMode::Snail,
+        ProfileMode::Constrictor,
+        ProfileMode::Solo,
+    ];
+
+    #[must_use]
+    pub fn for_rules(config: &RulesConfig) -> Self {
+        if config.growth == Growth::Constrictor {
+            ProfileMode::Constrictor
+        } else if config.solo {
+            ProfileMode::Solo
+        } else {
+            match config.hazards {
+                HazardSource::Royale => ProfileMode::Royale,
+                HazardSource::Snail => ProfileMode::Snail,
+                HazardSource::Static | HazardSource::Scheduled => ProfileMode::Standard,
+            }
+        }
+    }
+
+    /// Player counts a game of the mode can have
+    fn players(&self) -> RangeInclusive<usize> {
+        match self {
+            ProfileMode::Solo => 1..=1,
+            _ => 2..=MAX_PLAYERS,
+        }
+    }
+}
+
+/// Heuristic kind with its weights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "weights", rename_all = "snake_case")]
+pub enum HeuristicProfile {
+    Standard(StandardHeuristic),
+    Duels(DuelsHeuristic),
+    Royale(RoyaleHeuristic),
+    RoyaleDuels(RoyaleDuelsHeuristic),
+    Solo(SoloHeuristic),
+    Constrictor(ConstrictorHeuristic),
+}
+
+impl HeuristicProfile {
+    #[must_use]
+    pub fn build(&self) -> Arc<dyn Heuristic> {
+        match self {
+            HeuristicProfile::Standard(h) => Arc::new(*h),
+            HeuristicProfile::Duels(h) => Arc::new(*h),
+            HeuristicProfile::Royale(h) => Arc::new(*h),
+            HeuristicProfile::RoyaleDuels(h) => Arc::new(*h),
+            HeuristicProfile::Solo(h) => Arc::new(*h),
+            HeuristicProfile::Constrictor(h) => Arc::new(*h),
+        }
+    }
+}
+
+/// Heuristic used for a mode and a range of player counts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub mode: ProfileMode,
+    #[serde(default = "Profile::default_min_players")]
+    pub min_players: usize,
+    #[serde(default = "Profile::default_max_players")]
+    pub max_players: usize,
+    pub heuristic: HeuristicProfile,
+}
+
+impl Profile {
+    fn default_min_players() -> usize {
+        1
+    }
+
+    fn default_max_players() -> usize {
+        usize::MAX
+    }
+
+    fn players(&self) -> String {
+        match self.max_players {
+            usize::MAX => format!("{}+", self.min_players),
+            max => format!("{}-{}", self.min_players, max),
+        }
+    }
+
+    fn matches(&self, mode: ProfileMode, players: usize) -> bool {
+        self.mode == mode && (self.min_players..=self.max_players).contains(&players)
+    }
+}
+
+/// Maps game modes and player counts to heuristics, the first matching profile wins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileRegistry {
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for ProfileRegistry {
+    fn default() -> Self {
+        serde_json::from_str(DEFAULT_PROFILES).expect("built in profiles are invalid")
+    }
+}
+
+impl ProfileRegistry {
+    /// Read and validate a profile file, TOML if the extension is `.toml` and JSON otherwise
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+        let raw: serde_json::Value = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&file).map_err(|e| e.to_string()),
+            _ => serde_json::from_str(&file).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("could not parse {}: {}", path.display(), e))?;
+        let registry: ProfileRegistry = serde_json::from_value(raw.clone())
+            .map_err(|e| format!("could not parse {}: {}", path.display(), e))?;
+
+        registry.check_weights(&raw)?;
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    /// The heuristics ignore unknown weights, so the command line keeps accepting old tuner
+    /// output. A profile file is checked strictly, a typo would silently use the default
+    fn check_weights(&self, raw: &serde_json::Value) -> Result<(), String> {
+        let raw_profiles = raw["profiles"].as_array().into_iter().flatten();
+
+        for (i, (profile, raw)) in self.profiles.iter().zip(raw_profiles).enumerate() {
+            let known = serde_json::to_value(&profile.heuristic).map_err(|e| e.to_string())?;
+            let (Some(weights), Some(known)) = (
+                raw["heuristic"]["weights"].as_object(),
+                known["weights"].as_object(),
+            ) else {
+                continue;
+            };
+
+            if let Some(name) = weights.keys().find(|name| !known.contains_key(*name)) {
+                return Err(format!(
+                    "profile {} ({:?}) has an unknown weight `{}`",
+                    i, profile.mode, name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the weights of all duels and royale profiles, used to tune them from the
+    /// command line
+    #[must_use]
+    pub fn with_weights(
+        mut self,
+        duels: Option<DuelsHeuristic>,
+        royale: Option<RoyaleHeuristic>,
+    ) -> Self {
+        for profile in self.profiles.iter_mut() {
+            match &mut profile.heuristic {
+                HeuristicProfile::Duels(h) => *h = duels.unwrap_or(*h),
+                HeuristicProfile::Royale(h) => *h = royale.unwrap_or(*h),
+                _ => (),
+            }
+        }
+        self
+    }
+
+    /// Player ranges must be valid and every player count of every mode has to be covered
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, profile) in self.profiles.iter().enumerate() {
+            if profile.min_players == 0 || profile.min_players > profile.max_players {
+                return Err(format!(
+                    "profile {} ({:?}) has an invalid player range {}..={}",
+                    i, profile.mode, profile.min_players, profile.max_players
+                ));
+            }
+        }
+
+        for mode in ProfileMode::ALL {
+            if let Some(players) = mode.players().find(|p| self.find(mode, *p).is_none()) {
+                return Err(format!(
+                    "no profile for {:?} with {} players",
+                    mode, players
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn find(&self, mode: ProfileMode, players: usize) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.matches(mode, players))
+    }
+
+    /// Heuristic for the game, falls back to the standard heuristic for unusual player counts
+    #[must_use]
+    pub fn select(&self, config: &RulesConfig, players: usize) -> Arc<dyn Heuristic> {
+        match self.find(ProfileMode::for_rules(config), players) {
+            Some(profile) => profile.heuristic.build(),
+            None => Arc::new(StandardHeuristic::default()),
+        }
+    }
+}
+
+impl Display for ProfileRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for profile in self.profiles.iter() {
+            writeln!(
+                f,
+                "{:?} ({} players): {:?}",
+                profile.mode,
+                profile.players(),
+                profile.heuristic
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const TOML_PROFILES: &str = r#"
+[[profiles]]
+mode = "standard"
+min_players = 2
+max_players = 2
+heuristic = { kind = "duels", weights = { area = 2.0 } }
+
+[[profiles]]
+mode = "standard"
+heuristic = { kind = "standard", weights = {} }
+
+[[profiles]]
+mode = "royale"
+heuristic = { kind = "royale", weights = {} }
+
+[[profiles]]
+mode = "snail"
+heuristic = { kind = "duels", weights = {} }
+
+[[profiles]]
+mode = "constrictor"
+heuristic = { kind = "constrictor", weights = {} }
+
+[[profiles]]
+mode = "solo"
+heuristic = { kind = "solo", weights = {} }
+"#;
+
+    fn write_temp(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn default_profiles_are_valid() {
+        let registry = ProfileRegistry::default();
+        assert_eq!(registry.validate(), Ok(()));
+
+        let duels = registry.find(ProfileMode::Standard, 2).unwrap();
+        assert!(matches!(duels.heuristic, HeuristicProfile::Duels(_)));
+        let standard = registry.find(ProfileMode::Standard, 4).unwrap();
+        assert!(matches!(standard.heuristic, HeuristicProfile::Standard(_)));
+    }
+
+    #[test]
+    fn loads_json_and_toml() {
+        let json = write_temp("profiles.json", DEFAULT_PROFILES);
+        let toml = write_temp("profiles.toml", TOML_PROFILES);
+
+        assert_eq!(ProfileRegistry::load(&json).unwrap().profiles.len(), 7);
+
+        let registry = ProfileRegistry::load(&toml).unwrap();
+        match registry.find(ProfileMode::Standard, 2).unwrap().heuristic {
+            HeuristicProfile::Duels(duels) => assert_eq!(duels.area, 2.0),
+            _ => panic!("expected the duels profile"),
+        }
+
+        std::fs::remove_file(json).unwrap();
+        std::fs::remove_file(toml).unwrap();
+    }
+
+    #[test]
+    fn load_rejects_unknown_weights() {
+        let path = write_temp(
+            "unknown.toml",
+            &TOML_PROFILES.replace("area = 2.0", "aera = 2.0"),
+        );
+        assert!(ProfileRegistry::load(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+
+        assert!(ProfileRegistry::load(Path::new("missing.json")).is_err());
+    }
+
+    #[test]
+    fn command_line_weights_ignore_unknown_keys() {
+        // the tuner in optim/ still passes the old `hazard_area` of the duels heuristic
+        let duels: DuelsHeuristic = r#"{"area": 2.0, "hazard_area": 0.1}"#.parse().unwrap();
+        assert_eq!(duels.area, 2.0);
+    }
+
+    #[test]
+    fn validate_rejects_gaps_and_bad_ranges() {
+        let mut registry = ProfileRegistry::default();
+        registry.profiles[0].min_players = 3;
+        registry.profiles[0].max_players = 2;
+        assert!(registry.validate().is_err());
+
+        let mut registry = ProfileRegistry::default();
+        registry
+            .profiles
+            .retain(|p| !(p.mode == ProfileMode::Standard && p.min_players == 1));
+        assert_eq!(
+            registry.validate(),
+            Err("no profile for Standard with 3 players".to_string())
+        );
+    }
+
+    #[test]
+    fn command_line_weights_replace_profile_weights() {
+        let duels = DuelsHeuristic {
+            area: 7.0,
+            ..DuelsHeuristic::default()
+        };
+        let registry = ProfileRegistry::default().with_weights(Some(duels), None);
+
+        for profile in registry.profiles.iter() {
+            if let HeuristicProfile::Duels(h) = profile.heuristic {
+                assert_eq!(h.area, 7.0);
+            }
+        }
+    }
+}
@@ -1,10 +1,22 @@
-use std::str::FromStr;
 use crate::heuristic::floodfill::FloodType;
-use crate::heuristic::{Floodfill, Heuristic, StandardHeuristic};
+use crate::heuristic::{duel_enemy, Features, Floodfill, FoodRace, Heuristic, StandardHeuristic};
 use crate::simulation::{Outcome, State};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Names of the terms, in the order of the weights and the features
+const FEATURES: &[&str] = &[
+    "area",
+    "dead_end",
+    "health",
+    "length",
+    "food",
+    "alive_enemies",
+    "central",
+];
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RoyaleHeuristic {
     pub area: f32,
     pub health: f32,
@@ -12,6 +24,8 @@ pub struct RoyaleHeuristic {
     pub food: f32,
     pub alive_enemies: f32,
     pub central: f32,
+    /// Expand the flood by spent health instead of discounting the owned hazards
+    pub hazard_cost: bool,
 }
 
 impl Default for RoyaleHeuristic {
@@ -23,6 +37,7 @@ impl Default for RoyaleHeuristic {
             food: 1.0,
             alive_enemies: 4.0,
             central: 0.25,
+            hazard_cost: false,
         }
     }
 }
@@ -44,43 +59,99 @@ impl ToString for RoyaleHeuristic {
 impl Heuristic for RoyaleHeuristic {
     fn eval(&self, state: &State) -> Outcome {
         if !state.snakes[0].is_alive() {
-            return Outcome::Loss(state.snakes[0].loss_reason);
+            return Outcome::Loss(state.snakes[0].loss_reason());
         }
 
-        let floodmap = Floodfill::new(state, FloodType::FollowSnakes);
-
-        Outcome::Heuristic(self.calc_score(state, &floodmap))
+        let features = Floodfill::with(state, self.flood_type(state), |floodmap| {
+            self.snake_features(state, floodmap, 0)
+        });
+        Outcome::Heuristic(features.dot(&self.weights()))
     }
 
-    fn eval_all(&self, state: &State) -> Vec<Outcome> {
-        todo!()
+    fn weights(&self) -> Features {
+        Features::new(
+            FEATURES,
+            &[
+                self.area,
+                self.area,
+                self.health,
+                self.length,
+                self.food,
+                self.alive_enemies,
+                self.central,
+            ],
+        )
     }
-}
 
-impl RoyaleHeuristic {
-    pub fn calc_score(&self, state: &State, floodmap: &Floodfill) -> f32 {
-        let (own_cells, own_area_score) = self.area(floodmap, 0.4, 0.4, 0);
-        let (e_cells, e_area_score) = self.area(floodmap, 0.4, 0.4, 1);
-
-        let mut area_score = own_area_score / (e_area_score + own_area_score);
+    fn features(&self, state: &State) -> Vec<Option<Features>> {
+        Floodfill::with(state, self.flood_type(state), |floodmap| {
+            state
+                .snakes
+                .iter()
+                .enumerate()
+                .map(|(snake_id, snake)| {
+                    snake
+                        .is_alive()
+                        .then(|| self.snake_features(state, floodmap, snake_id))
+                })
+                .collect()
+        })
+    }
 
-        if floodmap.dead_ends[0] || floodmap.dead_ends[1] {
-            let area_diff = own_cells - e_cells;
-            area_score += area_diff;
+    /// Hazards either slow down the flood or discount the owned cells, never both
+    fn flood_type(&self, state: &State) -> FloodType {
+        if self.hazard_cost {
+            FloodType::HazardCost
+        } else {
+            FloodType::for_rules(&state.config)
         }
+    }
+}
 
-        let health_score = StandardHeuristic::health(state, 0);
-        let length_score = StandardHeuristic::length(state, 0);
-        let food_score = StandardHeuristic::food(state, floodmap, 0);
-        let alive_enemies_score = StandardHeuristic::alive_enemies(state, 0);
-        let central_score = StandardHeuristic::central(state, 0);
-
-        self.area * area_score
-            + self.health * health_score
-            + self.length * length_score
-            + self.food * food_score
-            + self.alive_enemies * alive_enemies_score
-            + self.central * central_score
+impl RoyaleHeuristic {
+    pub fn snake_features(&self, state: &State, floodmap: &Floodfill, snake_id: usize) -> Features {
+        // the area is compared with the first other snake that is still alive
+        let rival_id = duel_enemy(state, snake_id);
+
+        let hazard_discount = if self.hazard_cost {
+            1.0
+        } else {
+            state.config.settings.hazard_weight(0.4)
+        };
+        let (own_cells, own_area_score) = self.area(floodmap, 0.4, hazard_discount, snake_id);
+        let (e_cells, e_area_score) = self.area(floodmap, 0.4, hazard_discount, rival_id);
+
+        let area_score = own_area_score / (e_area_score + own_area_score);
+
+        let dead_end_score = if floodmap.dead_ends[snake_id] || floodmap.dead_ends[rival_id] {
+            own_cells - e_cells
+        } else {
+            0.0
+        };
+
+        let health_score = StandardHeuristic::health(state, snake_id);
+        let length_score = StandardHeuristic::length(state, snake_id);
+        // the races are not cached, skip them if food does not count
+        let food_score = if self.food == 0.0 {
+            0.0
+        } else {
+            FoodRace::score(&floodmap.food_races(), floodmap, snake_id)
+        };
+        let alive_enemies_score = StandardHeuristic::alive_enemies(state, snake_id);
+        let central_score = StandardHeuristic::central(state, floodmap, snake_id);
+
+        Features::new(
+            FEATURES,
+            &[
+                area_score,
+                dead_end_score,
+                health_score,
+                length_score,
+                food_score,
+                alive_enemies_score,
+                central_score,
+            ],
+        )
     }
 
     pub fn area(
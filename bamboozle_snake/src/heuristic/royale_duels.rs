@@ -1,11 +1,15 @@
 use crate::heuristic::floodfill::FloodType;
-use crate::heuristic::{Floodfill, Heuristic, StandardHeuristic};
+use crate::heuristic::{duel_enemy, Features, Floodfill, FoodRace, Heuristic, StandardHeuristic};
 use crate::simulation::{Outcome, Snake, State};
 
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+/// Names of the terms, in the order of the weights and the features
+const FEATURES: &[&str] = &["health", "area", "dead_end", "length", "food", "open"];
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RoyaleDuelsHeuristic {
     pub area: f32,
     pub snake_area: f32,
@@ -13,6 +17,10 @@ pub struct RoyaleDuelsHeuristic {
     pub health: f32,
     pub length: f32,
     pub food: f32,
+    /// Distance to the chokepoints of the own area, only on wrapped boards
+    pub open: f32,
+    /// Expand the flood by spent health instead of discounting the owned hazards
+    pub hazard_cost: bool,
 }
 
 impl Default for RoyaleDuelsHeuristic {
@@ -24,6 +32,8 @@ impl Default for RoyaleDuelsHeuristic {
             health: 0.05,
             length: 0.0,
             food: 0.0,
+            open: 0.05,
+            hazard_cost: false,
         }
     }
 }
@@ -45,54 +55,123 @@ impl ToString for RoyaleDuelsHeuristic {
 impl Heuristic for RoyaleDuelsHeuristic {
     fn eval(&self, state: &State) -> Outcome {
         if !state.snakes[0].is_alive() {
-            return Outcome::Loss(state.snakes[0].loss_reason);
+            return Outcome::Loss(state.snakes[0].loss_reason());
         }
-        let floodfill = Floodfill::new(state, FloodType::FollowSnakes);
-
-        let our_snake = &state.snakes[0];
-        let (enemy_id, enemy_snake) = state
-            .snakes
-            .iter()
-            .enumerate()
-            .skip(1)
-            .find(|(_, s)| s.is_alive())
-            .unwrap();
+        let features = Floodfill::with(state, self.flood_type(state), |floodfill| {
+            self.snake_features(state, floodfill, 0)
+        });
+        Outcome::Heuristic(features.dot(&self.weights()))
+    }
 
-        let health_score = self.health(our_snake);
-        let length_score = self.length(our_snake, enemy_snake);
-        let food_score = StandardHeuristic::food(state, &floodfill, 0);
+    fn weights(&self) -> Features {
+        Features::new(
+            FEATURES,
+            &[
+                self.health,
+                self.area,
+                self.area,
+                self.length,
+                self.food,
+                self.open,
+            ],
+        )
+    }
 
-        // area score
-        // let (our_cells, our_snake_cells, enemy_cells, enemy_snake_cells) = floodfill.count_duels();
+    fn features(&self, state: &State) -> Vec<Option<Features>> {
+        Floodfill::with(state, self.flood_type(state), |floodfill| {
+            state
+                .snakes
+                .iter()
+                .enumerate()
+                .map(|(snake_id, snake)| {
+                    snake
+                        .is_alive()
+                        .then(|| self.snake_features(state, floodfill, snake_id))
+                })
+                .collect()
+        })
+    }
 
-        let (our_owned, our_owned_hazards, our_owned_snakes, our_owned_snake_hazards) = floodfill.count_owned_royale(0);
-        let our_cell_sum = our_owned as f32 + self.snake_area * our_owned_snakes as f32 + our_owned_hazards as f32 * self.hazard_area + our_owned_snake_hazards as f32 * self.hazard_area / 2.0;
+    /// Hazards either slow down the flood or discount the owned cells, never both
+    fn flood_type(&self, state: &State) -> FloodType {
+        if self.hazard_cost {
+            FloodType::HazardCost
+        } else {
+            FloodType::for_rules(&state.config)
+        }
+    }
+}
 
-        let (en_owned, en_owned_hazards, en_owned_snakes, en_owned_snake_hazards) = floodfill.count_owned_royale(1);
-        let enemy_cell_sum = en_owned as f32 + self.snake_area * en_owned_snakes as f32 + en_owned_hazards as f32 * self.hazard_area + en_owned_snake_hazards as f32 * self.hazard_area / 2.0;
+impl RoyaleDuelsHeuristic {
+    pub fn snake_features(
+        &self,
+        state: &State,
+        floodfill: &Floodfill,
+        snake_id: usize,
+    ) -> Features {
+        let our_snake = &state.snakes[snake_id];
+        let enemy_id = duel_enemy(state, snake_id);
+        let enemy_snake = &state.snakes[enemy_id];
 
-        let mut area_score = our_cell_sum / (our_cell_sum + enemy_cell_sum);
+        let health_score = self.health(our_snake);
+        let length_score = self.length(our_snake, enemy_snake);
+        // the races are not cached, skip them if food does not count
+        let food_score = if self.food == 0.0 {
+            0.0
+        } else {
+            FoodRace::score(&floodfill.food_races(), floodfill, snake_id)
+        };
+        // the chambers are the most expensive term, skip them if they do not count
+        let open_score = if state.grid.wrapped && self.open != 0.0 {
+            StandardHeuristic::open(state, &floodfill.chambers(snake_id as u8), snake_id)
+        } else {
+            0.0
+        };
 
-        if floodfill.dead_ends[0] || floodfill.dead_ends[1] {
-            let area_diff =
-                (our_owned + our_owned_snakes + our_owned_hazards + our_owned_snake_hazards) as f32 - (en_owned + en_owned_snakes + en_owned_hazards + en_owned_snake_hazards) as f32;
-            area_score += area_diff;
-        }
+        // area score
+        let hazard_area = if self.hazard_cost {
+            1.0
+        } else {
+            state.config.settings.hazard_weight(self.hazard_area)
+        };
+        let (our_cells, our_cell_sum) = self.area(floodfill, hazard_area, snake_id);
+        let (enemy_cells, enemy_cell_sum) = self.area(floodfill, hazard_area, enemy_id);
+
+        let area_score = our_cell_sum / (our_cell_sum + enemy_cell_sum);
+
+        let dead_end_score = if floodfill.dead_ends[snake_id] || floodfill.dead_ends[enemy_id] {
+            (our_cells - enemy_cells) as f32
+        } else {
+            0.0
+        };
+
+        Features::new(
+            FEATURES,
+            &[
+                health_score,
+                area_score,
+                dead_end_score,
+                length_score,
+                food_score,
+                open_score,
+            ],
+        )
+    }
 
-        let score = self.health * health_score
-            + self.area * area_score
-            + self.length * length_score
-            + self.food * food_score;
+    /// Owned cells and their weighted sum, hazards and snake cells count less
+    fn area(&self, floodfill: &Floodfill, hazard_area: f32, snake_id: usize) -> (i32, f32) {
+        let (owned, owned_hazards, owned_snakes, owned_snake_hazards) =
+            floodfill.count_owned_royale(snake_id as u8);
 
-        Outcome::Heuristic(score)
-    }
+        let cells = (owned + owned_hazards + owned_snakes + owned_snake_hazards) as i32;
+        let cell_sum = owned as f32
+            + self.snake_area * owned_snakes as f32
+            + owned_hazards as f32 * hazard_area
+            + owned_snake_hazards as f32 * hazard_area / 2.0;
 
-    fn eval_all(&self, state: &State) -> Vec<Outcome> {
-        todo!()
+        (cells, cell_sum)
     }
-}
 
-impl RoyaleDuelsHeuristic {
     fn length(&self, our_snake: &Snake, enemy_snake: &Snake) -> f32 {
         our_snake.len() as f32 / (our_snake.len() as f32 + enemy_snake.len() as f32)
     }
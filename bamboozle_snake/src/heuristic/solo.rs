@@ -0,0 +1,199 @@
+use crate::heuristic::{CellFlood, Chambers, Features, Floodfill, Heuristic, StandardHeuristic};
+use crate::simulation::{Outcome, State};
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Names of the terms, in the order of the weights and the features
+const FEATURES: &[&str] = &["area", "food", "health", "length", "open"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoloHeuristic {
+    pub area: f32,
+    pub food: f32,
+    pub health: f32,
+    pub length: f32,
+    /// Distance to the chokepoints of the own area, only on wrapped boards
+    pub open: f32,
+}
+
+impl Default for SoloHeuristic {
+    fn default() -> Self {
+        Self {
+            area: 3.0,
+            food: 2.0,
+            health: 1.0,
+            length: 0.5,
+            open: 0.25,
+        }
+    }
+}
+
+impl FromStr for SoloHeuristic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
+}
+
+impl Display for SoloHeuristic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).unwrap())
+    }
+}
+
+impl Heuristic for SoloHeuristic {
+    fn eval(&self, state: &State) -> Outcome {
+        if !state.snakes[0].is_alive() {
+            return Outcome::Loss(state.snakes[0].loss_reason());
+        }
+
+        let features = Floodfill::with(state, self.flood_type(state), |floodfill| {
+            SoloHeuristic::snake_features(state, floodfill, 0)
+        });
+        Outcome::Heuristic(features.dot(&self.weights()))
+    }
+
+    fn weights(&self) -> Features {
+        Features::new(
+            FEATURES,
+            &[self.area, self.food, self.health, self.length, self.open],
+        )
+    }
+
+    fn features(&self, state: &State) -> Vec<Option<Features>> {
+        Floodfill::with(state, self.flood_type(state), |floodfill| {
+            state
+                .snakes
+                .iter()
+                .enumerate()
+                .map(|(snake_id, snake)| {
+                    snake
+                        .is_alive()
+                        .then(|| SoloHeuristic::snake_features(state, floodfill, snake_id))
+                })
+                .collect()
+        })
+    }
+}
+
+impl SoloHeuristic {
+    pub fn snake_features(state: &State, floodfill: &Floodfill, snake_id: usize) -> Features {
+        let chambers = floodfill.chambers(snake_id as u8);
+        let open_score = if state.grid.wrapped {
+            StandardHeuristic::open(state, &chambers, snake_id)
+        } else {
+            0.0
+        };
+
+        Features::new(
+            FEATURES,
+            &[
+                SoloHeuristic::area(state, &chambers),
+                SoloHeuristic::food(state, floodfill, snake_id),
+                SoloHeuristic::health(state, snake_id),
+                SoloHeuristic::length(state, snake_id),
+                open_score,
+            ],
+        )
+    }
+
+    /// Longest path we can still take in our area, as share of the board without the head
+    pub fn area(state: &State, chambers: &Chambers) -> f32 {
+        chambers.max_path() as f32 / (state.grid.cells.len() - 1) as f32
+    }
+
+    /// Only eat when we need it: nearest food must be reachable before we starve,
+    /// but eating much earlier just makes us longer.
+    /// Slack costs at most half of the score, so food we reach in time always beats food we
+    /// can not reach in time (0.0) and having no food at all (-1.0)
+    pub fn food(state: &State, floodfill: &Floodfill, snake_id: usize) -> f32 {
+        let health = state.snakes[snake_id].health as u32;
+
+        let nearest_food = state
+            .food
+            .iter()
+            .filter_map(|food| match floodfill.cells[*food] {
+                CellFlood::Owned { id, step, .. } if id as usize == snake_id => Some(step),
+                _ => None,
+            })
+            .min();
+
+        match nearest_food {
+            // with health equal to the distance we eat on the last turn
+            Some(dist) if dist <= health => {
+                // slack between reaching the food and starving, below 1.0
+                let slack = (health - dist) as f32 / 100.0;
+                1.0 - slack * 0.5
+            }
+            Some(_) => 0.0,
+            None => -1.0,
+        }
+    }
+
+    pub fn health(state: &State, snake_id: usize) -> f32 {
+        (state.snakes[snake_id].health.max(0) as f32 / 100.0).sqrt()
+    }
+
+    /// Longer snakes leave less room to move in
+    pub fn length(state: &State, snake_id: usize) -> f32 {
+        -(state.snakes[snake_id].len() as f32 / state.grid.cells.len() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Coord, GameState};
+    use crate::heuristic::FloodType;
+    use crate::test_util::{game, snake, with_rules};
+
+    fn solo_game(body: &[(i32, i32)], health: u32, food: &[(i32, i32)]) -> GameState {
+        let you = snake("you", body.iter().copied(), health);
+        let mut game = with_rules(&game(vec![you]), "solo", "standard");
+        game.board.food = food.iter().map(|(x, y)| Coord { x: *x, y: *y }).collect();
+        game
+    }
+
+    fn food_score(health: u32, food: &[(i32, i32)]) -> f32 {
+        let state = State::from(&solo_game(&[(5, 5), (5, 4), (5, 3)], health, food));
+        Floodfill::with(&state, FloodType::for_rules(&state.config), |floodfill| {
+            SoloHeuristic::food(&state, floodfill, 0)
+        })
+    }
+
+    #[test]
+    fn area_is_the_longest_path() {
+        // the head sits on the smaller color of a 3x3 board: the flood owns the whole board,
+        // but a path can only alternate through 7 of the 8 other cells
+        let mut game = solo_game(&[(1, 0), (1, 1), (1, 2)], 100, &[]);
+        game.board.width = 3;
+        game.board.height = 3;
+        let state = State::from(&game);
+
+        Floodfill::with(&state, FloodType::for_rules(&state.config), |floodfill| {
+            let (owned, owned_snake) = floodfill.count_owned(0);
+            assert_eq!(owned + owned_snake, 9);
+            let chambers = floodfill.chambers(0);
+            assert_eq!(SoloHeuristic::area(&state, &chambers), 7.0 / 8.0);
+        });
+    }
+
+    #[test]
+    fn food_is_best_just_before_starving() {
+        let last = food_score(2, &[(5, 7)]);
+        let late = food_score(3, &[(5, 7)]);
+        let early = food_score(100, &[(5, 7)]);
+
+        assert_eq!(last, 1.0);
+        assert_eq!(late, 1.0 - 0.01 * 0.5);
+        assert_eq!(early, 1.0 - 0.98 * 0.5);
+        assert!(last > late && late > early && early > 0.0);
+        // the flood stops where we starve, so food one step too far counts as no food
+        assert_eq!(food_score(1, &[(5, 7)]), -1.0);
+        assert_eq!(food_score(100, &[]), -1.0);
+    }
+}
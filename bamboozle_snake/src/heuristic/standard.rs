@@ -1,11 +1,27 @@
 use crate::coord;
 use crate::game::Coord;
-use crate::heuristic::floodfill::FloodType;
-use crate::heuristic::{CellFlood, Floodfill, Heuristic};
-use crate::simulation::{Outcome, State};
-use itertools::Itertools;
-
-#[derive(Debug, Clone)]
+use crate::heuristic::{CellFlood, Chambers, Features, Floodfill, Heuristic};
+use crate::simulation::{CellType, Outcome, State};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::iter::zip;
+use std::str::FromStr;
+
+/// Names of the terms, in the order of the weights and the features
+const FEATURES: &[&str] = &[
+    "area",
+    "dead_end",
+    "health",
+    "length",
+    "alive_enemies",
+    "central",
+    "food",
+    "space",
+    "survival",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StandardHeuristic {
     pub area: f32,
     pub health: f32,
@@ -13,6 +29,8 @@ pub struct StandardHeuristic {
     pub alive_enemies: f32,
     pub food: f32,
     pub central: f32,
+    pub space: f32,
+    pub survival: f32,
 }
 
 impl Default for StandardHeuristic {
@@ -24,61 +42,130 @@ impl Default for StandardHeuristic {
             alive_enemies: 4.0,
             food: 1.0,
             central: 0.25,
+            space: 0.0,
+            survival: 0.0,
         }
     }
 }
 
+impl FromStr for StandardHeuristic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
+}
+
+impl Display for StandardHeuristic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).unwrap())
+    }
+}
+
 impl Heuristic for StandardHeuristic {
     fn eval(&self, state: &State) -> Outcome {
         if !state.snakes[0].is_alive() {
-            return Outcome::Loss(state.snakes[0].loss_reason);
+            return Outcome::Loss(state.snakes[0].loss_reason());
         }
 
-        let floodmap = Floodfill::new(state, FloodType::FollowSnakes);
-
-        Outcome::Heuristic(self.calc_score(state, &floodmap, 0))
+        let features = Floodfill::with(state, self.flood_type(state), |floodmap| {
+            self.snake_features(state, floodmap, 0)
+        });
+        Outcome::Heuristic(features.dot(&self.weights()))
     }
 
-    fn eval_all(&self, state: &State) -> Vec<Outcome> {
-        let mut outcomes = Vec::with_capacity(state.snakes.len());
-
-        let floodmap = Floodfill::new(state, FloodType::Simple);
+    fn weights(&self) -> Features {
+        Features::new(
+            FEATURES,
+            &[
+                self.area,
+                self.area,
+                self.health,
+                self.length,
+                self.alive_enemies,
+                self.central,
+                self.food,
+                self.space,
+                self.survival,
+            ],
+        )
+    }
 
-        for (snake_id, snake) in state.snakes.iter().enumerate() {
-            if !snake.is_alive() {
-                outcomes.push(Outcome::Loss(snake.loss_reason));
-                continue;
-            }
-            outcomes.push(Outcome::Heuristic(
-                self.calc_score(state, &floodmap, snake_id),
-            ));
-        }
-        outcomes
+    fn features(&self, state: &State) -> Vec<Option<Features>> {
+        // all snakes are scored on the flood of `eval` (not `FloodType::Simple`), so the
+        // score of snake 0 in `eval_all` matches `eval`
+        Floodfill::with(state, self.flood_type(state), |floodmap| {
+            state
+                .snakes
+                .iter()
+                .enumerate()
+                .map(|(snake_id, snake)| {
+                    snake
+                        .is_alive()
+                        .then(|| self.snake_features(state, floodmap, snake_id))
+                })
+                .collect()
+        })
     }
 }
 
 impl StandardHeuristic {
-    pub fn calc_score(&self, state: &State, floodmap: &Floodfill, snake_id: usize) -> f32 {
-        let mut area_score = self.area(floodmap, 0.0, snake_id);
-        if floodmap.dead_ends[0] && floodmap.dead_ends.iter().skip(1).any(|d| !*d) {
-            area_score -= 10.0;
-        }
+    pub fn snake_features(&self, state: &State, floodmap: &Floodfill, snake_id: usize) -> Features {
+        let area_score = StandardHeuristic::area(floodmap, 0.0, snake_id);
+        let dead_ends = &floodmap.dead_ends;
+        let others_escape = dead_ends
+            .iter()
+            .enumerate()
+            .any(|(i, d)| i != snake_id && !*d);
+        let dead_end_score = if dead_ends[snake_id] && others_escape {
+            -10.0
+        } else {
+            0.0
+        };
 
         let health_score = StandardHeuristic::health(state, snake_id);
         let length_score = StandardHeuristic::length(state, snake_id);
         let alive_enemies_score = StandardHeuristic::alive_enemies(state, snake_id);
-        let central_score = StandardHeuristic::central(state, snake_id);
         let food_score = StandardHeuristic::food(state, floodmap, snake_id);
-
-        self.area * area_score
-            + self.health * health_score
-            + self.length * length_score
-            + self.alive_enemies * alive_enemies_score
-            + self.central * central_score
-            + self.food * food_score
+        // the chambers are the most expensive term, compute them once and only if they count
+        let open = state.grid.wrapped && self.central != 0.0;
+        let chambers = (self.space != 0.0 || open).then(|| floodmap.chambers(snake_id as u8));
+        let central_score = match &chambers {
+            Some(chambers) if open => StandardHeuristic::open(state, chambers, snake_id),
+            // wrapped boards have no centre
+            _ if state.grid.wrapped => 0.0,
+            _ => StandardHeuristic::centre(state, snake_id),
+        };
+        let space_score = if self.space == 0.0 {
+            0.0
+        } else {
+            chambers.as_deref().map_or(0.0, Chambers::fill_ratio)
+        };
+        let survival_score = if self.survival == 0.0 {
+            0.0
+        } else {
+            floodmap
+                .survival(snake_id)
+                .score(state.snakes[snake_id].len())
+        };
+
+        Features::new(
+            FEATURES,
+            &[
+                area_score,
+                dead_end_score,
+                health_score,
+                length_score,
+                alive_enemies_score,
+                central_score,
+                food_score,
+                space_score,
+                survival_score,
+            ],
+        )
     }
 
-    pub fn area(&self, floodmap: &Floodfill, snake_discount: f32, snake_id: usize) -> f32 {
+    pub fn area(floodmap: &Floodfill, snake_discount: f32, snake_id: usize) -> f32 {
         let (owned_cells, owned_snake_cells) = floodmap.count_owned(snake_id as u8);
 
         let snake_length_sum = floodmap
@@ -110,8 +197,9 @@ impl StandardHeuristic {
         let max_other_length = state
             .snakes
             .iter()
-            .skip(1)
-            .map(|s| s.len())
+            .enumerate()
+            .filter(|(i, _)| *i != snake_id)
+            .map(|(_, s)| s.len())
             .max()
             .unwrap_or(0);
 
@@ -126,40 +214,75 @@ impl StandardHeuristic {
     }
 
     pub fn food(state: &State, floodmap: &Floodfill, snake_id: usize) -> f32 {
-        let mut food_dists = Vec::with_capacity(state.food.len());
-
         // get owned foods
-        for food in state.food.iter() {
-            if let CellFlood::Owned { id, step, .. } = floodmap.cells[*food] {
-                if id as usize == snake_id {
-                    food_dists.push(step);
-                }
-            }
-        }
+        let food_dists = state
+            .food
+            .iter()
+            .filter_map(|food| match floodmap.cells[*food] {
+                CellFlood::Owned { id, step, .. } if id as usize == snake_id => Some(step),
+                _ => None,
+            });
 
+        StandardHeuristic::food_score(state, floodmap, snake_id, food_dists)
+    }
+
+    /// Score of the distances to the foods of the snake, food that is expected to spawn in its
+    /// area fills the slots of missing foods
+    pub fn food_score(
+        state: &State,
+        floodmap: &Floodfill,
+        snake_id: usize,
+        food_dists: impl IntoIterator<Item = u32>,
+    ) -> f32 {
         // only care about 3 nearest foods
-        if food_dists.len() > 3 {
-            food_dists = food_dists.into_iter().sorted().take(3).collect();
+        let mut nearest = [u32::MAX; 3];
+        for food_dist in food_dists {
+            if food_dist < nearest[2] {
+                nearest[2] = food_dist;
+                nearest.sort_unstable();
+            }
         }
+        let food_dists = &nearest[..nearest.iter().filter(|d| **d != u32::MAX).count()];
 
         // dont care about food too far away
-        let max_dist = (state.grid.width + state.grid.height) as u32;
+        let max_dist = state.grid.max_dist();
 
         let mut score = 0.0;
         for food_dist in food_dists.iter() {
             score += max_dist.saturating_sub(*food_dist) as f32 / max_dist as f32;
         }
 
+        // food that is expected to spawn in our area fills the remaining slots
+        if food_dists.len() < 3 && state.food_chance > 0.0 {
+            let mut expected_score = 0.0;
+
+            for (flood_cell, grid_cell) in zip(floodmap.cells.cells.iter(), state.grid.cells.iter())
+            {
+                if let CellFlood::Owned { id, step, .. } = flood_cell {
+                    if *id as usize == snake_id
+                        && grid_cell.cell == CellType::Free
+                        && grid_cell.hazard == 0
+                    {
+                        expected_score += state.food_chance * max_dist.saturating_sub(*step) as f32
+                            / max_dist as f32;
+                    }
+                }
+            }
+            score += expected_score.min((3 - food_dists.len()) as f32);
+        }
+
         score / 3.0
     }
 
     pub fn alive_enemies(state: &State, snake_id: usize) -> f32 {
-        let num_of_other_snakes = state.snakes.len() - 1;
+        let is_enemy = |i: usize| i != snake_id && !state.is_ally(snake_id, i);
+
+        let num_of_other_snakes = (0..state.snakes.len()).filter(|i| is_enemy(*i)).count();
         let num_other_alive = state
             .snakes
             .iter()
             .enumerate()
-            .filter(|(i, s)| *i != snake_id && s.is_alive())
+            .filter(|(i, s)| is_enemy(*i) && s.is_alive())
             .count();
 
         if num_of_other_snakes == 0 {
@@ -169,7 +292,17 @@ impl StandardHeuristic {
         1.0 - (num_other_alive as f32 / num_of_other_snakes as f32)
     }
 
-    pub fn central(state: &State, snake_id: usize) -> f32 {
+    /// Closeness to the centre, away from the walls. Wrapped boards have neither, there the
+    /// distance to the chokepoints of the own area counts, narrow seam crossings included.
+    pub fn central(state: &State, floodmap: &Floodfill, snake_id: usize) -> f32 {
+        if state.grid.wrapped {
+            let chambers = floodmap.chambers(snake_id as u8);
+            return StandardHeuristic::open(state, &chambers, snake_id);
+        }
+        StandardHeuristic::centre(state, snake_id)
+    }
+
+    fn centre(state: &State, snake_id: usize) -> f32 {
         let dist_to_center = coord!(state.grid.width as i32 / 2, state.grid.height as i32 / 2)
             .manhattan_dist(&state.snakes[snake_id].head());
 
@@ -179,4 +312,18 @@ impl StandardHeuristic {
             1.0 / dist_to_center as f32
         }
     }
+
+    /// 1.0 without chokepoints in the area, less the closer the nearest one is to the head.
+    /// Articulation points and narrow seam crossings both count as chokepoint
+    pub fn open(state: &State, chambers: &Chambers, snake_id: usize) -> f32 {
+        let head = state.snakes[snake_id].head();
+
+        chambers
+            .articulation_points
+            .iter()
+            .chain(&chambers.seam_chokepoints)
+            .map(|pos| state.grid.manhattan_dist(&head, pos))
+            .min()
+            .map_or(1.0, |dist| 1.0 - 1.0 / (dist + 1) as f32)
+    }
 }
@@ -1,6 +1,9 @@
+pub mod bitboard;
 pub mod game;
 pub mod grid;
 pub mod heuristic;
 pub mod logic;
 pub mod simulation;
+#[cfg(test)]
+mod test_util;
 pub mod tree;
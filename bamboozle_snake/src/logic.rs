@@ -1,19 +1,18 @@
 use clap::Parser;
 use std::convert::Infallible;
-use std::sync::Arc;
+use std::path::PathBuf;
 use std::time::Instant;
 
 use crate::game::{Direction, GameState};
 use log::{debug, error, info, warn};
 use once_cell::sync::Lazy;
+use serde::Deserialize;
 use serde_json::json;
 use yansi::Paint;
 
-use crate::heuristic::{ConstrictorHeuristic, RoyaleDuelsHeuristic};
-use crate::simulation::{Mode, State};
-use crate::tree::iterative_search_mt;
-
-use crate::heuristic::{DuelsHeuristic, Heuristic, RoyaleHeuristic, StandardHeuristic};
+use crate::heuristic::{DuelsHeuristic, ProfileRegistry, RoyaleHeuristic};
+use crate::simulation::{Elimination, RulesConfig, State};
+use crate::tree::{explain, iterative_search_mt};
 
 #[derive(Parser, Debug)]
 #[command(author, long_about = None)]
@@ -28,14 +27,45 @@ pub struct Args {
     pub port: u16,
     #[arg(long, default_value_t = String::from("bamboozle snake"))]
     pub name: String,
-    #[arg(long, default_value_t = DuelsHeuristic::default())]
-    pub duel_heuristic: DuelsHeuristic,
-    #[arg(long, default_value_t = RoyaleHeuristic::default())]
-    pub royal_heuristic: RoyaleHeuristic,
+    /// JSON weights of every duels profile
+    #[arg(long)]
+    pub duel_heuristic: Option<DuelsHeuristic>,
+    /// JSON weights of every royale profile
+    #[arg(long)]
+    pub royal_heuristic: Option<RoyaleHeuristic>,
+    /// JSON or TOML file mapping game modes and player counts to heuristics
+    #[arg(long)]
+    pub profile: Option<PathBuf>,
+    /// Log the score breakdown of the principal line after each move
+    #[arg(long)]
+    pub explain: bool,
+}
+
+/// Search depth of the explain endpoint if the request does not set one
+const EXPLAIN_DEPTH: u32 = 4;
+
+#[derive(Deserialize, Debug)]
+pub struct ExplainQuery {
+    pub depth: Option<u32>,
 }
 
 pub static CONFIG: Lazy<Args> = Lazy::new(Args::parse);
 
+pub static PROFILES: Lazy<ProfileRegistry> = Lazy::new(|| {
+    let registry = match &CONFIG.profile {
+        Some(path) => ProfileRegistry::load(path),
+        None => Ok(ProfileRegistry::default()),
+    };
+
+    match registry {
+        Ok(registry) => registry.with_weights(CONFIG.duel_heuristic, CONFIG.royal_heuristic),
+        Err(e) => {
+            error!("Invalid heuristic profiles: {}", e);
+            std::process::exit(1);
+        }
+    }
+});
+
 pub fn handle_start(game_state: GameState) {
     let mut snakes = "".to_string();
     let num_snakes = game_state.board.snakes.len();
@@ -49,7 +79,9 @@ pub fn handle_start(game_state: GameState) {
 
     info!(
         "Start ({:?}): {} ({})",
-        State::determine_mode(&game_state), game_state.game.id, snakes
+        RulesConfig::from(&game_state),
+        game_state.game.id,
+        snakes
     );
 }
 
@@ -58,23 +90,8 @@ pub async fn handle_move(game_state: GameState) -> Result<impl warp::Reply, Infa
 
     let action = tokio::task::spawn_blocking(move || {
         let player_count = game_state.board.snakes.len();
-        let heuristic: Arc<dyn Heuristic>;
-
-        let mode = State::determine_mode(&game_state);
-
-        if mode == Mode::Constrictor {
-            heuristic = Arc::new(ConstrictorHeuristic::default());
-        } else if mode == Mode::Royale {
-            if player_count == 2 {
-                heuristic = Arc::new(RoyaleDuelsHeuristic::default());
-            } else {
-                heuristic = Arc::new(CONFIG.royal_heuristic);
-            }
-        } else if mode == Mode::Standard {
-            heuristic = Arc::new(StandardHeuristic::default());
-        } else {
-            heuristic = Arc::new(CONFIG.duel_heuristic);
-        }
+        let heuristic = PROFILES.select(&RulesConfig::from(&game_state), player_count);
+
         debug!("using {:?} in step {}", heuristic, game_state.turn);
         iterative_search_mt(game_state, heuristic)
     })
@@ -96,18 +113,52 @@ pub async fn handle_move(game_state: GameState) -> Result<impl warp::Reply, Infa
     Ok(warp::reply::json(&json!({"move":action.to_string()})))
 }
 
+pub async fn handle_explain(
+    query: ExplainQuery,
+    game_state: GameState,
+) -> Result<impl warp::Reply, Infallible> {
+    let depth = query.depth.unwrap_or(EXPLAIN_DEPTH).clamp(1, CONFIG.max_depth);
+
+    let explanation = tokio::task::spawn_blocking(move || {
+        let player_count = game_state.board.snakes.len();
+        let heuristic = PROFILES.select(&RulesConfig::from(&game_state), player_count);
+
+        let explanation = explain(&State::from(&game_state), heuristic.as_ref(), depth);
+        debug!("{}", explanation);
+        explanation
+    })
+        .await;
+
+    Ok(match explanation {
+        Ok(explanation) => warp::reply::with_status(
+            warp::reply::json(&explanation),
+            warp::http::StatusCode::OK,
+        ),
+        Err(e) => {
+            error!("Explain failed: {}", e);
+            warp::reply::with_status(
+                warp::reply::json(&json!({"error": e.to_string()})),
+                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+            )
+        }
+    })
+}
+
 pub fn handle_end(game_state: GameState) {
     let outcome;
 
     if game_state.board.snakes.is_empty() {
         outcome = Paint::yellow("Draw").to_string();
-    } else if game_state.board.snakes[0] == game_state.you {
+    } else if game_state.board.snakes.iter().any(|s| {
+        *s == game_state.you || (!s.squad.is_empty() && s.squad == game_state.you.squad)
+    }) {
         outcome = Paint::green("Win").to_string();
     } else {
         outcome = format!(
-            "{} (winner: {})",
+            "{} (winner: {}, {})",
             Paint::red("Loss"),
-            game_state.board.snakes[0].name
+            game_state.board.snakes[0].name,
+            describe_elimination(&game_state)
         );
     }
 
@@ -116,3 +167,24 @@ pub fn handle_end(game_state: GameState) {
         outcome, game_state.turn, game_state.game.id
     );
 }
+
+fn describe_elimination(game_state: &GameState) -> String {
+    let Some(elimination) = Elimination::infer(game_state) else {
+        return "elimination unknown".to_string();
+    };
+
+    // indices of other snakes are shifted by one, we are the first snake
+    let by = elimination.by.and_then(|i| {
+        game_state
+            .board
+            .snakes
+            .iter()
+            .filter(|s| **s != game_state.you)
+            .nth(i as usize - 1)
+    });
+
+    match by {
+        Some(snake) => format!("{:?} with {}", elimination.cause, snake.name),
+        None => format!("{:?}", elimination.cause),
+    }
+}
@@ -1,10 +1,12 @@
 use std::env;
-use log::debug;
+use log::{debug, info};
 use serde_json::json;
 use warp::Filter;
 
 use bamboozle_snake::game::GameState;
-use bamboozle_snake::logic::{handle_end, handle_move, handle_start, CONFIG};
+use bamboozle_snake::logic::{
+    handle_end, handle_explain, handle_move, handle_start, ExplainQuery, CONFIG, PROFILES,
+};
 
 #[tokio::main]
 async fn main() {
@@ -14,6 +16,7 @@ async fn main() {
 
     env_logger::init();
     debug!("{:?}", *CONFIG);
+    info!("Heuristic profiles:\n{}", *PROFILES);
 
     let index_endpoint = warp::get().and(warp::path::end()).map(|| {
         warp::reply::json(&json!({
@@ -39,6 +42,13 @@ async fn main() {
         .and(warp::body::json::<GameState>())
         .and_then(handle_move);
 
+    // score breakdown of the root moves for debugging, `?depth=` sets the search depth
+    let explain_endpoint = warp::path("explain")
+        .and(warp::post())
+        .and(warp::query::<ExplainQuery>())
+        .and(warp::body::json::<GameState>())
+        .and_then(handle_explain);
+
     let end_endpoint = warp::path("end")
         .and(warp::post())
         .and(warp::body::json::<GameState>())
@@ -51,6 +61,7 @@ async fn main() {
         index_endpoint
             .or(start_endpoint)
             .or(move_endpoint)
+            .or(explain_endpoint)
             .or(end_endpoint),
     )
         .run(([0, 0, 0, 0], CONFIG.port))
@@ -0,0 +1,172 @@
+use crate::bitboard::{BitGrid, Bitboard};
+use crate::game::Direction;
+use crate::simulation::{MoveSet, State, MAX_SNAKES};
+
+/// Bitboard view of a state, one bitset per snake plus food and hazards
+#[derive(Clone, Debug)]
+pub struct BitState<'a> {
+    pub state: &'a State,
+    pub grid: BitGrid,
+    /// body of every alive snake (empty for dead snakes)
+    pub bodies: [Bitboard; MAX_SNAKES],
+    /// cells that block a move in the next turn (bodies without tails that move away)
+    pub obstacles: [Bitboard; MAX_SNAKES],
+    pub heads: [usize; MAX_SNAKES],
+    pub food: Bitboard,
+    pub hazards: Bitboard,
+}
+
+impl<'a> From<&'a State> for BitState<'a> {
+    fn from(state: &'a State) -> Self {
+        assert!(BitState::supports(state), "state too large for bitboards");
+        let grid = BitGrid::new(state.grid.width, state.grid.height, state.grid.wrapped);
+
+        let mut bodies = [Bitboard::EMPTY; MAX_SNAKES];
+        let mut obstacles = [Bitboard::EMPTY; MAX_SNAKES];
+        let mut heads = [0; MAX_SNAKES];
+
+        for (i, snake) in state.snakes.iter().enumerate() {
+            heads[i] = grid.index(snake.head());
+            if !snake.is_alive() {
+                continue;
+            }
+
+            for pos in snake.body() {
+                bodies[i].set(grid.index(*pos));
+            }
+            // same as the grid: a tail is only free if it is not stacked
+            for pos in snake.body().take(snake.len() - 1) {
+                obstacles[i].set(grid.index(*pos));
+            }
+        }
+
+        let mut food = Bitboard::EMPTY;
+        for pos in state.food.iter().filter(|p| grid.contains(**p)) {
+            food.set(grid.index(*pos));
+        }
+
+        let mut hazards = Bitboard::EMPTY;
+        for pos in state.hazards.iter().filter(|p| grid.contains(**p)) {
+            hazards.set(grid.index(*pos));
+        }
+
+        Self {
+            state,
+            grid,
+            bodies,
+            obstacles,
+            heads,
+            food,
+            hazards,
+        }
+    }
+}
+
+impl<'a> BitState<'a> {
+    /// Board fits into bitboards (up to 25x25) and the snakes into the bitsets per snake
+    #[must_use]
+    pub fn supports(state: &State) -> bool {
+        BitGrid::fits(state.grid.width, state.grid.height) && state.snakes.len() <= MAX_SNAKES
+    }
+
+    /// Cells that block the snake, allied bodies do not if squads may collide
+    #[must_use]
+    pub fn blocked(&self, snake_i: usize) -> Bitboard {
+        let allow_allies = self.state.config.settings.squad.allow_body_collisions;
+
+        (0..self.state.snakes.len())
+            .filter(|i| !(allow_allies && self.state.is_ally(snake_i, *i)))
+            .fold(Bitboard::EMPTY, |acc, i| acc | self.obstacles[i])
+    }
+
+    /// Same moves as `State::valid_moves`, from one shift per direction
+    #[must_use]
+    pub fn valid_moves(&self, snake_i: usize) -> MoveSet {
+        let snake = &self.state.snakes[snake_i];
+
+        if !snake.should_simulate || !snake.is_alive() {
+            return MoveSet::NONE;
+        }
+
+        let head = Bitboard::single(self.heads[snake_i]);
+        let free = self.grid.board & !self.blocked(snake_i);
+        let mut moves = MoveSet::new(snake.last_action);
+
+        for dir in Direction::get_alive_actions() {
+            if !(self.grid.step(head, dir) & free).is_empty() {
+                moves.insert(dir);
+            }
+        }
+
+        if moves.is_empty() {
+            moves.insert(Direction::Up);
+        }
+        moves
+    }
+
+    #[must_use]
+    pub fn get_valid_actions(&self, snake_i: usize) -> Vec<Direction> {
+        self.valid_moves(snake_i).iter().collect()
+    }
+
+    /// Cells that more than one alive head is on
+    #[must_use]
+    pub fn head_collisions(&self) -> Bitboard {
+        let mut seen = Bitboard::EMPTY;
+        let mut collisions = Bitboard::EMPTY;
+
+        for (snake, head) in self.state.snakes.iter().zip(self.heads) {
+            if !snake.is_alive() {
+                continue;
+            }
+            if seen.get(head) {
+                collisions.set(head);
+            }
+            seen.set(head);
+        }
+        collisions
+    }
+}
+
+impl State {
+    /// Bitboard representation of the state, check `BitState::supports` for large boards
+    #[must_use]
+    pub fn bits(&self) -> BitState<'_> {
+        BitState::from(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{load_games, random_actions, with_rules, RULESETS};
+
+    #[test]
+    fn moves_match_the_state() {
+        let mut rng = rand::thread_rng();
+
+        for game in load_games() {
+            for (ruleset, map) in RULESETS {
+                for _ in 0..10 {
+                    let mut state = State::from(&with_rules(&game, ruleset, map));
+
+                    while !state.is_end_state() && state.turn < game.turn + 100 {
+                        let bits = state.bits();
+                        for i in 0..state.snakes.len() {
+                            assert_eq!(
+                                bits.get_valid_actions(i),
+                                state.get_valid_actions(i),
+                                "{ruleset} {map} turn {} snake {i}",
+                                state.turn
+                            );
+                        }
+                        assert!(bits.head_collisions().is_empty());
+
+                        let actions = random_actions(&state, &mut rng);
+                        state = state.step(&actions);
+                    }
+                }
+            }
+        }
+    }
+}
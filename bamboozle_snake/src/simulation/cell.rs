@@ -61,12 +61,12 @@ impl Grid<CellGame> {
                 continue;
             }
 
-            for pos in snake.body[..snake.body.len() - 1].iter() {
+            for pos in snake.body().take(snake.len() - 1) {
                 self[*pos] = CellGame::new(CellType::Snake(i as u8));
             }
             // tail coord is "unique", mark as free in next turn
-            if snake.len() == 1 || *snake.tail() != snake.body[snake.body.len() - 2] {
-                self[*snake.body.last().unwrap()] = CellGame::new(CellType::Tail(i as u8));
+            if !snake.has_stacked_tail() {
+                self[*snake.tail()] = CellGame::new(CellType::Tail(i as u8));
             }
         }
 
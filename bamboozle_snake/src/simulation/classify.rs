@@ -0,0 +1,295 @@
+use crate::game::{Coord, Direction};
+use crate::simulation::{CellType, LossType, State};
+
+/// Risk of a move, a snake takes the worst label that applies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveClass {
+    /// certain elimination (wall, body, starvation or hazard damage)
+    Death(LossType),
+    /// the region behind the move has less cells than the snake is long and no safe loop
+    DeadEnd {
+        area: u16,
+    },
+    /// a longer snake can move to the same cell
+    HeadLoss {
+        by: u8,
+    },
+    /// a snake of the same length can move to the same cell, both would die
+    HeadTrade {
+        by: u8,
+    },
+    /// tail of another snake that stays in place if that snake eats
+    EnemyTail {
+        of: u8,
+    },
+    /// entering a hazard costs health
+    Hazard {
+        damage: i16,
+    },
+    Safe,
+}
+
+impl MoveClass {
+    /// Higher is safer, used to order moves
+    #[must_use]
+    pub fn score(&self) -> i32 {
+        match self {
+            MoveClass::Death(reason) => -20_000 + *reason as i32,
+            MoveClass::DeadEnd { area } => -10_000 + *area as i32,
+            MoveClass::HeadLoss { .. } => -300,
+            MoveClass::HeadTrade { .. } => -200,
+            MoveClass::EnemyTail { .. } => -150,
+            MoveClass::Hazard { damage } => -(*damage as i32),
+            MoveClass::Safe => 0,
+        }
+    }
+
+    #[must_use]
+    pub fn is_death(&self) -> bool {
+        matches!(self, MoveClass::Death(_))
+    }
+}
+
+impl State {
+    /// Label every direction of the snake with its risk, indexed by `Direction as usize`
+    #[must_use]
+    pub fn classify_moves(&self, snake_i: usize) -> [MoveClass; 4] {
+        let mut classes = [MoveClass::Safe; 4];
+
+        for dir in Direction::get_alive_actions() {
+            classes[dir as usize] = self.classify_move(snake_i, dir);
+        }
+        classes
+    }
+
+    /// Direction with the safest label, ties go to the last action
+    #[must_use]
+    pub fn safest_move(&self, snake_i: usize) -> Direction {
+        let classes = self.classify_moves(snake_i);
+        let last_action = self.snakes[snake_i].last_action;
+
+        let mut best = (Direction::Up, i32::MIN);
+        for dir in [last_action]
+            .into_iter()
+            .chain(Direction::get_alive_actions())
+        {
+            if dir != Direction::None && classes[dir as usize].score() > best.1 {
+                best = (dir, classes[dir as usize].score());
+            }
+        }
+        best.0
+    }
+
+    fn classify_move(&self, snake_i: usize, dir: Direction) -> MoveClass {
+        let snake = &self.snakes[snake_i];
+        let pos = snake.head().step(dir);
+
+        if !self.grid.contains(pos) {
+            return MoveClass::Death(LossType::WallCollision);
+        }
+        if !self.is_valid_move(snake_i, pos) {
+            return match self.grid[pos].cell {
+                CellType::Snake(si) if si as usize == snake_i => {
+                    MoveClass::Death(LossType::SelfCollision)
+                }
+                _ => MoveClass::Death(LossType::SnakeCollision),
+            };
+        }
+
+        let is_food = self.grid.is_food(pos);
+        let damage = if is_food {
+            0
+        } else {
+            self.config.settings.hazard_damage_per_turn * self.grid[pos].hazard as i16
+        };
+        if !is_food && snake.health - 1 - damage <= 0 {
+            let reason = if damage > 0 {
+                LossType::Hazard
+            } else {
+                LossType::Starvation
+            };
+            return MoveClass::Death(reason);
+        }
+
+        let area = self.region_size(snake_i, pos, snake.len());
+        if area < snake.len() && !self.survival_from(snake_i, pos, 1).safe_loop {
+            return MoveClass::DeadEnd { area: area as u16 };
+        }
+
+        // a possible loss outweighs a trade
+        let mut head_class = None;
+        for (i, other) in self.snakes.iter().enumerate() {
+            if i == snake_i
+                || !other.is_alive()
+                || self.grid.manhattan_dist(&other.head(), &pos) != 1
+            {
+                continue;
+            }
+
+            if other.len() > snake.len() {
+                return MoveClass::HeadLoss { by: i as u8 };
+            } else if other.len() == snake.len() {
+                head_class = Some(MoveClass::HeadTrade { by: i as u8 });
+            }
+        }
+        if let Some(class) = head_class {
+            return class;
+        }
+
+        if let CellType::Tail(si) = self.grid[pos].cell {
+            let other = &self.snakes[si as usize];
+            let can_eat = other
+                .head()
+                .get_neighbours()
+                .into_iter()
+                .any(|n| self.grid.contains(n) && self.grid.is_food(n));
+
+            if si as usize != snake_i && can_eat {
+                return MoveClass::EnemyTail { of: si };
+            }
+        }
+
+        if damage > 0 {
+            return MoveClass::Hazard { damage };
+        }
+        MoveClass::Safe
+    }
+
+    /// Cells reachable from `start` without crossing bodies, counting stops at `limit`
+    fn region_size(&self, snake_i: usize, start: Coord, limit: usize) -> usize {
+        let index = |pos: Coord| {
+            let x = pos.x.rem_euclid(self.grid.width as i32) as usize;
+            let y = pos.y.rem_euclid(self.grid.height as i32) as usize;
+            y * self.grid.width + x
+        };
+
+        let mut visited = vec![false; self.grid.width * self.grid.height];
+        let mut stack = vec![start];
+        visited[index(start)] = true;
+        let mut area = 0;
+
+        while let Some(pos) = stack.pop() {
+            area += 1;
+            if area >= limit {
+                break;
+            }
+
+            for next in pos.get_neighbours() {
+                if self.grid.contains(next)
+                    && !visited[index(next)]
+                    && self.is_valid_move(snake_i, next)
+                {
+                    visited[index(next)] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{game, snake};
+
+    #[test]
+    fn move_into_own_pocket_is_dead_end() {
+        // the head closes a 2x2 pocket in the corner with its own body
+        let body = [
+            (2, 1),
+            (2, 0),
+            (3, 0),
+            (3, 1),
+            (3, 2),
+            (2, 2),
+            (1, 2),
+            (0, 2),
+        ]
+        .into_iter()
+        // the body only frees the pocket long after the snake is stuck
+        .chain((3..10).map(|y| (0, y)));
+
+        let state = State::from(&game(vec![snake("you", body, 100)]));
+        let classes = state.classify_moves(0);
+
+        assert_eq!(
+            classes[Direction::Left as usize],
+            MoveClass::DeadEnd { area: 4 }
+        );
+        assert_eq!(
+            classes[Direction::Down as usize],
+            MoveClass::Death(LossType::SelfCollision)
+        );
+        assert!(classes[Direction::Up as usize].is_death());
+        assert!(classes[Direction::Right as usize].is_death());
+        assert_eq!(state.safest_move(0), Direction::Left);
+    }
+
+    #[test]
+    fn longer_enemy_next_to_the_cell_is_head_loss() {
+        let you = snake("you", [(5, 5), (5, 4), (5, 3)], 100);
+        let enemy = snake("enemy", [(7, 5), (8, 5), (9, 5), (10, 5)], 100);
+        let state = State::from(&game(vec![you, enemy]));
+        let classes = state.classify_moves(0);
+
+        assert_eq!(
+            classes[Direction::Right as usize],
+            MoveClass::HeadLoss { by: 1 }
+        );
+        assert_eq!(classes[Direction::Up as usize], MoveClass::Safe);
+        assert_ne!(state.safest_move(0), Direction::Right);
+    }
+
+    #[test]
+    fn enemy_of_the_same_length_is_head_trade() {
+        let you = snake("you", [(5, 5), (5, 4), (5, 3)], 100);
+        let enemy = snake("enemy", [(7, 5), (8, 5), (9, 5)], 100);
+        let state = State::from(&game(vec![you, enemy]));
+        let classes = state.classify_moves(0);
+
+        assert_eq!(
+            classes[Direction::Right as usize],
+            MoveClass::HeadTrade { by: 1 }
+        );
+        assert_eq!(classes[Direction::Left as usize], MoveClass::Safe);
+    }
+
+    #[test]
+    fn tail_of_an_enemy_next_to_food_is_risky() {
+        let you = snake("you", [(5, 5), (4, 5), (3, 5)], 100);
+        let enemy = snake("enemy", [(7, 7), (7, 6), (6, 6), (6, 5)], 100);
+        let mut game = game(vec![you, enemy]);
+
+        let state = State::from(&game);
+        assert_eq!(
+            state.classify_moves(0)[Direction::Right as usize],
+            MoveClass::Safe
+        );
+
+        // the enemy might eat and keep its tail in place
+        game.board.food = vec![Coord { x: 7, y: 8 }];
+        let state = State::from(&game);
+        assert_eq!(
+            state.classify_moves(0)[Direction::Right as usize],
+            MoveClass::EnemyTail { of: 1 }
+        );
+    }
+
+    #[test]
+    fn hazard_costs_its_damage() {
+        let mut game = game(vec![snake("you", [(5, 5), (5, 4), (5, 3)], 100)]);
+        game.board.hazards = vec![Coord { x: 6, y: 5 }];
+        game.game.ruleset.settings.hazard_damage_per_turn = 14;
+
+        let state = State::from(&game);
+        let classes = state.classify_moves(0);
+
+        assert_eq!(
+            classes[Direction::Right as usize],
+            MoveClass::Hazard { damage: 14 }
+        );
+        assert_eq!(classes[Direction::Left as usize], MoveClass::Safe);
+        assert_ne!(state.safest_move(0), Direction::Right);
+    }
+}
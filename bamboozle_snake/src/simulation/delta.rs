@@ -0,0 +1,305 @@
+use crate::game::{Coord, Direction};
+use crate::simulation::outcome::{Elimination, LossType};
+use crate::simulation::zobrist::SnakeKey;
+use crate::simulation::{CellGame, CellType, FoodSpawn, RoyaleShrink, State};
+use std::iter::zip;
+
+/// Changes of `State::apply` that are needed to restore the previous state
+#[derive(Debug, Clone)]
+pub struct StateDelta(DeltaKind);
+
+#[derive(Debug, Clone)]
+enum DeltaKind {
+    Incremental(IncrementalDelta),
+    /// Full copy of the previous state for rules that can not be undone cheaply
+    Snapshot(Box<State>),
+}
+
+#[derive(Debug, Clone)]
+struct IncrementalDelta {
+    turn: u32,
+    hash: u64,
+    food_chance: f32,
+    royale: Option<RoyaleShrink>,
+    hazards_len: usize,
+    snakes: Vec<SnakeDelta>,
+    /// index in the food vector and position of eaten food
+    eaten_food: Vec<(usize, Coord)>,
+    /// previous value of every written cell (in write order)
+    cells: Vec<(Coord, CellGame)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SnakeDelta {
+    key: SnakeKey,
+    last_action: Direction,
+    elimination: Option<Elimination>,
+    moved: bool,
+}
+
+impl State {
+    /// Step the state in place, the returned delta restores it with `undo`
+    pub fn apply(&mut self, actions: &[Direction]) -> StateDelta {
+        debug_assert!(actions.len() == self.snakes.len());
+
+        if !self.is_reversible() {
+            let old_state = self.clone();
+            *self = old_state.step(actions);
+            return StateDelta(DeltaKind::Snapshot(Box::new(old_state)));
+        }
+
+        let constrictor = self.config.is_constrictor();
+        let mut delta = IncrementalDelta {
+            turn: self.turn,
+            hash: self.hash,
+            food_chance: self.food_chance,
+            royale: self.royale,
+            hazards_len: self.hazards.len(),
+            snakes: Vec::with_capacity(self.snakes.len()),
+            eaten_food: Vec::new(),
+            cells: Vec::with_capacity(self.snakes.len() * 6),
+        };
+
+        // the grid still belongs to the previous turn while the snakes move
+        let mut snakes = std::mem::take(&mut self.snakes);
+
+        for (snake, action) in zip(snakes.iter_mut(), actions) {
+            let moved = snake.is_alive();
+            delta.snakes.push(SnakeDelta {
+                key: SnakeKey::from(&*snake),
+                last_action: snake.last_action,
+                elimination: snake.elimination,
+                moved,
+            });
+
+            if moved && constrictor {
+                snake.step_constrictor(*action);
+            } else if moved {
+                snake.step(*action);
+            }
+            if moved {
+                self.wrap_head(snake);
+            }
+        }
+
+        State::check_head_collisions(&mut snakes, self.turn + 1);
+        self.check_collisions(&mut snakes);
+
+        if !constrictor {
+            for snake in snakes.iter_mut().filter(|s| s.is_alive()) {
+                let head = snake.head();
+                let hazard = self.grid[head].hazard;
+                snake.health -= self.config.settings.hazard_damage_per_turn * hazard as i16;
+
+                if self.grid.is_food(head) {
+                    snake.eat();
+                    self.food_chance = 0.0;
+
+                    while let Some(i) = self.food.iter().position(|f| *f == head) {
+                        self.food.remove(i);
+                        self.hash ^= self.food_key(head);
+                        delta.eaten_food.push((i, head));
+                    }
+                }
+                if snake.health <= 0 {
+                    let cause = if hazard > 0 {
+                        LossType::Hazard
+                    } else {
+                        LossType::Starvation
+                    };
+                    snake.die(cause, self.turn + 1, None);
+                }
+            }
+        }
+
+        self.snakes = snakes;
+        self.hash ^= self.phase_key();
+        self.turn += 1;
+        self.hash ^= self.phase_key();
+
+        if !constrictor {
+            self.apply_squad_rules();
+        }
+        self.update_grid(&mut delta);
+
+        for (i, old) in delta.snakes.iter().enumerate().filter(|(_, s)| s.moved) {
+            self.hash ^= self.snake_hash_delta(i, &old.key, &self.snakes[i], !constrictor);
+        }
+
+        if !constrictor {
+            let map = self.config.map;
+            map.update(self);
+            self.spawn_food();
+        }
+
+        StateDelta(DeltaKind::Incremental(delta))
+    }
+
+    /// Restore the state before the `apply` that returned `delta`
+    pub fn undo(&mut self, delta: StateDelta) {
+        let delta = match delta.0 {
+            DeltaKind::Snapshot(state) => {
+                *self = *state;
+                return;
+            }
+            DeltaKind::Incremental(delta) => delta,
+        };
+
+        // hazards were added after the cells were written
+        for pos in self.hazards.drain(delta.hazards_len..) {
+            self.grid[pos].hazard -= 1;
+        }
+        for (pos, cell) in delta.cells.into_iter().rev() {
+            self.grid[pos] = cell;
+        }
+        for (i, pos) in delta.eaten_food.into_iter().rev() {
+            self.food.insert(i, pos);
+        }
+
+        for (snake, old) in zip(self.snakes.iter_mut(), delta.snakes) {
+            if old.moved {
+                snake.step_back(old.key.len, old.key.tail);
+            }
+            snake.health = old.key.health;
+            snake.last_action = old.last_action;
+            snake.elimination = old.elimination;
+        }
+
+        self.turn = delta.turn;
+        self.hash = delta.hash;
+        self.food_chance = delta.food_chance;
+        self.royale = delta.royale;
+    }
+
+    /// Rules that only change the state in ways a compact delta can restore
+    fn is_reversible(&self) -> bool {
+        // allied bodies may overlap and the order snakes are drawn in matters
+        let overlapping_allies =
+            self.config.squad && self.config.settings.squad.allow_body_collisions;
+        // constrictor steps drop all food and hazards
+        let constrictor_items =
+            self.config.is_constrictor() && !(self.food.is_empty() && self.hazards.is_empty());
+
+        self.config.map.is_reversible()
+            && self.food_spawn != FoodSpawn::Sampled
+            && !overlapping_allies
+            && !constrictor_items
+    }
+
+    /// Update the cells of moved snakes, the result matches a freshly filled grid
+    fn update_grid(&mut self, delta: &mut IncrementalDelta) {
+        // clear the cells the snakes left
+        for (i, (snake, old)) in zip(&self.snakes, &delta.snakes).enumerate() {
+            if !old.moved {
+                continue;
+            }
+
+            // the new head of a dead snake was never drawn
+            let skip = if snake.is_alive() { snake.len() } else { 1 };
+            let left_cells = snake.body().skip(skip);
+
+            for pos in left_cells.chain(std::iter::once(&old.key.tail)) {
+                if let CellType::Snake(id) | CellType::Tail(id) = self.grid[*pos].cell {
+                    if id == i as u8 {
+                        delta.cells.push((*pos, self.grid[*pos]));
+                        self.grid[*pos].cell = CellType::Free;
+                    }
+                }
+            }
+        }
+
+        // eaten food is gone even if the snake was eliminated afterwards
+        for (_, pos) in delta.eaten_food.iter() {
+            if self.grid.is_food(*pos) {
+                delta.cells.push((*pos, self.grid[*pos]));
+                self.grid[*pos].cell = CellType::Free;
+            }
+        }
+
+        // draw the cells that changed at the head and the tail
+        for (i, snake) in self.snakes.iter().enumerate().filter(|(_, s)| s.is_alive()) {
+            let last = snake.len() - 1;
+            let before_tail = snake
+                .body()
+                .nth(last.saturating_sub(1))
+                .filter(|_| last > 2);
+
+            for pos in snake.body().take(2.min(last)).chain(before_tail) {
+                delta.cells.push((*pos, self.grid[*pos]));
+                self.grid[*pos].cell = CellType::Snake(i as u8);
+            }
+
+            let tail = *snake.tail();
+            delta.cells.push((tail, self.grid[tail]));
+            self.grid[tail].cell = if snake.has_stacked_tail() {
+                CellType::Snake(i as u8)
+            } else {
+                CellType::Tail(i as u8)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+    use crate::test_util::{load_games, random_actions, with_rules, RULESETS};
+
+    fn assert_same(state: &State, expected: &State, context: &str) {
+        assert_eq!(state.turn, expected.turn, "{context}");
+        assert_eq!(state.snakes, expected.snakes, "{context}");
+        assert_eq!(state.grid.cells, expected.grid.cells, "{context}");
+        assert_eq!(state.food, expected.food, "{context}");
+        assert_eq!(state.hazards, expected.hazards, "{context}");
+        assert_eq!(state.food_chance, expected.food_chance, "{context}");
+        assert_eq!(state.royale, expected.royale, "{context}");
+        assert_eq!(state.spiral, expected.spiral, "{context}");
+        assert_eq!(state.hash, expected.hash, "{context}");
+    }
+
+    /// Every rule variant plus allied snakes that may overlap
+    fn games() -> Vec<(String, GameState)> {
+        let mut games = Vec::new();
+        for game in load_games() {
+            for (ruleset, map) in RULESETS {
+                games.push((format!("{ruleset} {map}"), with_rules(&game, ruleset, map)));
+            }
+            let mut squad = with_rules(&game, "squad", "standard");
+            squad.game.ruleset.settings.squad.allow_body_collisions = true;
+            games.push(("squad overlapping".to_string(), squad));
+        }
+        games
+    }
+
+    #[test]
+    fn apply_matches_step_and_undo_restores() {
+        let mut rng = rand::thread_rng();
+        let (mut incremental, mut snapshots) = (0, 0);
+
+        for (context, game) in games() {
+            for _ in 0..10 {
+                let mut state = State::from(&game);
+
+                while !state.is_end_state() && state.turn < game.turn + 100 {
+                    let actions = random_actions(&state, &mut rng);
+                    let old_state = state.clone();
+                    let stepped = state.step(&actions);
+
+                    let delta = state.apply(&actions);
+                    match delta.0 {
+                        DeltaKind::Incremental(_) => incremental += 1,
+                        DeltaKind::Snapshot(_) => snapshots += 1,
+                    }
+                    assert_same(&state, &stepped, &context);
+
+                    state.undo(delta);
+                    assert_same(&state, &old_state, &context);
+
+                    state = stepped;
+                }
+            }
+        }
+        assert!(incremental > 0 && snapshots > 0);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::game::Coord;
+use crate::simulation::{CellGame, CellType, State};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// How new food is simulated in `State::step`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FoodSpawn {
+    None,
+    /// track the probability of spawned food on each free cell
+    #[default]
+    Expected,
+    /// place food randomly like the game engine does (used for rollouts)
+    Sampled,
+}
+
+impl State {
+    /// Probability that `pos` holds food in this state
+    #[must_use]
+    pub fn food_probability(&self, pos: Coord) -> f32 {
+        if self.grid.is_food(pos) {
+            1.0
+        } else if self.is_food_spawn_cell(pos) {
+            self.food_chance
+        } else {
+            0.0
+        }
+    }
+
+    /// Cells food can spawn on: free, without hazard and not next to a head.
+    /// Maps with fixed spawn points only need the point to be free
+    #[must_use]
+    pub fn is_food_spawn_cell(&self, pos: Coord) -> bool {
+        if !self.config.map.spawns_food() {
+            return self.config.map.food_spawn_points().contains(&pos)
+                && self.grid.contains(pos)
+                && self.grid[pos].cell == CellType::Free;
+        }
+
+        let cell = &self.grid[pos];
+        cell.cell == CellType::Free
+            && cell.hazard == 0
+            && !self
+                .snakes
+                .iter()
+                .filter(|s| s.is_alive())
+                .any(|s| self.grid.manhattan_dist(&s.head(), &pos) <= 1)
+    }
+
+    fn food_spawn_cells(&self) -> impl Iterator<Item = Coord> + '_ {
+        (0..self.grid.height as i32)
+            .flat_map(move |y| (0..self.grid.width as i32).map(move |x| Coord { x, y }))
+            .filter(|pos| self.is_food_spawn_cell(*pos))
+    }
+
+    /// Spawn new food after the snakes moved (expects a filled grid)
+    pub fn spawn_food(&mut self) {
+        if !self.config.map.spawns_food() && self.config.map.food_spawn_points().is_empty() {
+            return;
+        }
+
+        match self.food_spawn {
+            FoodSpawn::None => (),
+            FoodSpawn::Expected => self.spawn_food_expected(),
+            FoodSpawn::Sampled => self.spawn_food_sampled(),
+        }
+    }
+
+    fn spawn_food_expected(&mut self) {
+        let spawn_cells = self.food_spawn_cells().count();
+
+        if spawn_cells == 0 {
+            return;
+        }
+
+        // already spawned food counts towards the minimum
+        let expected_food = self.food.len() as f32 + self.food_chance * spawn_cells as f32;
+        let minimum_food = self.config.settings.minimum_food as f32;
+
+        // fixed spawn points get no minimum, only the spawn chance
+        let expected_spawns = if expected_food < minimum_food && self.config.map.spawns_food() {
+            minimum_food - expected_food
+        } else {
+            self.config.settings.food_spawn_chance as f32 / 100.0
+        };
+
+        let cell_chance = (expected_spawns / spawn_cells as f32).min(1.0);
+        self.food_chance = 1.0 - (1.0 - self.food_chance) * (1.0 - cell_chance);
+    }
+
+    fn spawn_food_sampled(&mut self) {
+        let mut rng = rand::thread_rng();
+
+        let minimum_food = if self.config.map.spawns_food() {
+            self.config.settings.minimum_food as usize
+        } else {
+            0
+        };
+
+        let num_food = if self.food.len() < minimum_food {
+            minimum_food - self.food.len()
+        } else if rng.gen_range(0..100) < self.config.settings.food_spawn_chance {
+            1
+        } else {
+            0
+        };
+
+        if num_food == 0 {
+            return;
+        }
+
+        let spawn_cells: Vec<Coord> = self.food_spawn_cells().collect();
+
+        for pos in spawn_cells.choose_multiple(&mut rng, num_food) {
+            self.hash ^= self.food_key(*pos);
+            self.food.push(*pos);
+            self.grid[*pos] = CellGame {
+                cell: CellType::Food,
+                ..self.grid[*pos]
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Coord, Direction};
+    use crate::simulation::State;
+    use crate::test_util::load_games;
+
+    #[test]
+    fn expected_food_fills_up_to_minimum() {
+        for mut game in load_games() {
+            game.game.ruleset.settings.minimum_food = 5;
+            let mut state = State::from(&game);
+            state.spawn_food();
+
+            let expected: f32 = (0..state.grid.height as i32)
+                .flat_map(|y| (0..state.grid.width as i32).map(move |x| Coord { x, y }))
+                .map(|pos| state.food_probability(pos))
+                .sum();
+
+            // cells next to heads can not get food and must not dilute the chance
+            assert!((expected - 5.0).abs() < 1e-3, "{expected}");
+        }
+    }
+
+    #[test]
+    fn eating_resets_food_chance() {
+        let mut state = State::from(&load_games()[0]);
+        let actions: Vec<Direction> = (0..state.snakes.len())
+            .map(|i| state.get_valid_actions(i)[0])
+            .collect();
+
+        state.food = vec![state.snakes[0].head().step(actions[0])];
+        state.fill_grid();
+        state.food_chance = 0.5;
+        // nothing new spawns, so only the reset changes the chance
+        state.config.settings.minimum_food = 0;
+        state.config.settings.food_spawn_chance = 0;
+
+        let stepped = state.step(&actions);
+        assert!(stepped.snakes[0].is_alive());
+        assert_eq!(stepped.food_chance, 0.0);
+
+        state.apply(&actions);
+        assert_eq!(state.food_chance, 0.0);
+    }
+}
@@ -0,0 +1,454 @@
+use crate::game::{Coord, Direction, Settings};
+use crate::grid::Grid;
+use crate::simulation::{CellGame, CellType, HazardSource, RoyaleShrink, State};
+use std::fmt::Debug;
+use std::iter::zip;
+
+/// Hazards of hz_spiral grow by one cell every few turns
+const SPIRAL_EVERY_N_TURNS: u32 = 3;
+
+/// The only cells arcade_maze places food on
+const ARCADE_MAZE_FOOD: [Coord; 12] = [
+    Coord { x: 1, y: 1 },
+    Coord { x: 3, y: 11 },
+    Coord { x: 4, y: 7 },
+    Coord { x: 4, y: 17 },
+    Coord { x: 9, y: 1 },
+    Coord { x: 9, y: 5 },
+    Coord { x: 9, y: 11 },
+    Coord { x: 9, y: 17 },
+    Coord { x: 14, y: 7 },
+    Coord { x: 14, y: 17 },
+    Coord { x: 15, y: 11 },
+    Coord { x: 17, y: 1 },
+];
+
+/// Map specific rules that are applied after the snakes moved
+pub trait MapRules: Debug + Send + Sync {
+    /// Prepare the initial state created from the game state
+    fn init(&self, _state: &mut State) {}
+
+    /// Update hazards and food of the new state, its grid is already filled
+    fn update(&self, _state: &mut State) {}
+
+    /// Same as `update` for maps that need to look at the previous state
+    fn update_from(&self, state: &mut State, _old_state: &State) {
+        self.update(state);
+    }
+
+    /// `update` only adds hazards to the end of the hazard vector, so undoing a step
+    /// does not need the previous state
+    fn is_reversible(&self) -> bool {
+        true
+    }
+
+    /// Whether food spawns randomly on free cells
+    fn spawns_food(&self) -> bool {
+        true
+    }
+
+    /// Fixed cells food spawns on if it does not spawn randomly
+    fn food_spawn_points(&self) -> &'static [Coord] {
+        &[]
+    }
+
+    fn hazard_source(&self) -> HazardSource {
+        HazardSource::Static
+    }
+
+    /// Turns after which the hazard schedule repeats, 0 if no hazards are due on fixed turns
+    fn schedule_period(&self, _settings: &Settings) -> u32 {
+        0
+    }
+}
+
+#[must_use]
+pub fn map_rules(map: &str) -> &'static dyn MapRules {
+    match map {
+        "royale" => &RoyaleMap,
+        "snail_mode" => &SnailMode,
+        "hz_spiral" => &Spiral,
+        "arcade_maze" => &ArcadeMaze,
+        "hz_islands_bridges" => &IslandsBridges,
+        "hz_rings" => &Rings,
+        _ => &StandardMap,
+    }
+}
+
+#[derive(Debug)]
+pub struct StandardMap;
+
+impl MapRules for StandardMap {}
+
+/// Static maze of hazard walls, one food at a time spawns on a few fixed spots
+#[derive(Debug)]
+pub struct ArcadeMaze;
+
+impl MapRules for ArcadeMaze {
+    fn spawns_food(&self) -> bool {
+        false
+    }
+
+    fn food_spawn_points(&self) -> &'static [Coord] {
+        &ARCADE_MAZE_FOOD
+    }
+}
+
+/// Static hazard water around the islands, food spawns like on the standard map
+#[derive(Debug)]
+pub struct IslandsBridges;
+
+impl MapRules for IslandsBridges {}
+
+/// Hazard rings stay where the board puts them, food spawns like on the standard map
+#[derive(Debug)]
+pub struct Rings;
+
+impl MapRules for Rings {}
+
+#[derive(Debug)]
+pub struct RoyaleMap;
+
+impl MapRules for RoyaleMap {
+    fn hazard_source(&self) -> HazardSource {
+        HazardSource::Royale
+    }
+
+    fn schedule_period(&self, settings: &Settings) -> u32 {
+        settings.royale.shrink_every_n_turns
+    }
+
+    fn init(&self, state: &mut State) {
+        state.royale = Some(RoyaleShrink::from_grid(&state.grid));
+    }
+
+    fn update(&self, state: &mut State) {
+        if let Some(mut royale) = state.royale {
+            royale.step(state.turn, &state.config.settings);
+
+            state.hash ^= State::royale_key(&state.royale) ^ State::royale_key(&Some(royale));
+            state.royale = Some(royale);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SnailMode;
+
+impl MapRules for SnailMode {
+    fn hazard_source(&self) -> HazardSource {
+        HazardSource::Snail
+    }
+
+    fn is_reversible(&self) -> bool {
+        false
+    }
+
+    fn update_from(&self, state: &mut State, old_state: &State) {
+        // dont use the hazards vector in snail mode
+        state.hazards.clear();
+        state.fill_grid();
+
+        // set hazards to reduced old value
+        for (old_cell, new_cell) in zip(old_state.grid.cells.iter(), state.grid.cells.iter_mut()) {
+            if old_cell.hazard > 1 {
+                new_cell.hazard = old_cell.hazard.saturating_sub(1);
+            }
+        }
+
+        // add new hazards
+        for old_snake in old_state.snakes.iter().filter(|s| s.is_alive()) {
+            // hazards spawn only if tail "disappeared" (snake did not eat food)
+            if !old_snake.has_stacked_tail() {
+                let cell = &mut state.grid[*old_snake.tail()];
+                if !matches!(cell.cell, CellType::Snake(_)) {
+                    cell.hazard = old_snake.len() as u8;
+                }
+            }
+        }
+
+        // all hazards on the board changed
+        state.hash = state.compute_hash();
+    }
+}
+
+/// Hazards grow in a spiral around a random center
+#[derive(Debug)]
+pub struct Spiral;
+
+impl MapRules for Spiral {
+    fn hazard_source(&self) -> HazardSource {
+        HazardSource::Scheduled
+    }
+
+    fn schedule_period(&self, _settings: &Settings) -> u32 {
+        SPIRAL_EVERY_N_TURNS
+    }
+
+    fn init(&self, state: &mut State) {
+        state.spiral = SpiralShape::find(state);
+    }
+
+    fn update(&self, state: &mut State) {
+        if !state.turn.is_multiple_of(SPIRAL_EVERY_N_TURNS) {
+            return;
+        }
+
+        let next = state
+            .spiral
+            .and_then(|spiral| spiral.cells(&state.grid).nth(state.hazards.len()));
+        if let Some(pos) = next {
+            state.add_hazard(pos);
+        }
+    }
+}
+
+/// Center and direction of the hz_spiral hazards, they stay the same for the whole game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpiralShape {
+    center: Coord,
+    first_dir: Direction,
+    clockwise: bool,
+}
+
+impl SpiralShape {
+    /// The only spiral that covers exactly the current hazards, in any order.
+    /// None while the hazards still fit several spirals.
+    #[must_use]
+    pub fn find(state: &State) -> Option<Self> {
+        let num_hazards = state.hazards.len();
+        let mut found = None;
+
+        for center in state.hazards.iter() {
+            for first_dir in Direction::get_alive_actions() {
+                for clockwise in [true, false] {
+                    let spiral = SpiralShape {
+                        center: *center,
+                        first_dir,
+                        clockwise,
+                    };
+                    let matches = spiral
+                        .cells(&state.grid)
+                        .take(num_hazards)
+                        .filter(|pos| state.grid[*pos].hazard > 0)
+                        .count()
+                        == num_hazards;
+
+                    if matches && found.replace(spiral).is_some_and(|f| f != spiral) {
+                        return None;
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Cells of the square spiral that are on the board, in the order they turn into hazards
+    pub fn cells<'a>(&self, grid: &'a Grid<CellGame>) -> impl Iterator<Item = Coord> + 'a {
+        let clockwise = self.clockwise;
+        let mut pos = self.center;
+        let mut dir = self.first_dir;
+        let mut arm_length = 1;
+        let mut arm_step = 0;
+        let mut arm = 0;
+        let max_steps = (grid.width.max(grid.height) + 1).pow(2);
+
+        let walk = (0..max_steps).map(move |_| {
+            pos = pos.step(dir);
+            arm_step += 1;
+            if arm_step == arm_length {
+                dir = SpiralShape::turn(dir, clockwise);
+                arm_step = 0;
+                // arms grow every second turn
+                arm = (arm + 1) % 2;
+                if arm == 0 {
+                    arm_length += 1;
+                }
+            }
+            pos
+        });
+
+        std::iter::once(self.center)
+            .chain(walk)
+            .filter(|pos| grid.contains(*pos))
+    }
+
+    fn turn(dir: Direction, clockwise: bool) -> Direction {
+        match (dir, clockwise) {
+            (Direction::Up, true) | (Direction::Down, false) => Direction::Right,
+            (Direction::Right, true) | (Direction::Left, false) => Direction::Down,
+            (Direction::Down, true) | (Direction::Up, false) => Direction::Left,
+            (Direction::Left, true) | (Direction::Right, false) => Direction::Up,
+            (Direction::None, _) => Direction::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+    use crate::simulation::FoodSpawn;
+    use crate::test_util::{load_games, with_rules};
+
+    /// Steps the game with the first valid actions, `apply` has to agree with `step`
+    /// and only eaten food may disappear
+    fn play(game: &GameState, turns: u32) -> Vec<State> {
+        let mut state = State::from(game);
+        state.food_spawn = FoodSpawn::None;
+        let mut states = vec![state.clone()];
+
+        for _ in 0..turns {
+            let actions: Vec<Direction> = (0..state.snakes.len())
+                .map(|i| state.get_valid_actions(i)[0])
+                .collect();
+            let stepped = state.step(&actions);
+            state.apply(&actions);
+
+            assert_eq!(state.hazards, stepped.hazards);
+            assert_eq!(state.food, stepped.food);
+            assert_eq!(state.grid.cells, stepped.grid.cells);
+
+            let heads: Vec<Coord> = stepped.alive_snakes().iter().map(|s| s.head()).collect();
+            let mut uneaten = states.last().unwrap().food.clone();
+            uneaten.retain(|f| !heads.contains(f));
+            assert_eq!(stepped.food, uneaten);
+
+            states.push(stepped);
+        }
+        states
+    }
+
+    #[test]
+    fn standard_map_keeps_hazards() {
+        for mut game in load_games() {
+            game.board.hazards = vec![Coord { x: 0, y: 0 }, Coord { x: 10, y: 10 }];
+
+            for state in play(&game, 6) {
+                assert_eq!(state.hazards, game.board.hazards);
+            }
+        }
+    }
+
+    #[test]
+    fn royale_map_marks_possible_sides() {
+        for game in load_games() {
+            let mut game = with_rules(&game, "standard", "royale");
+            game.board.hazards = (0..11).map(|y| Coord { x: 0, y }).collect();
+            game.turn = 1;
+
+            let states = play(&game, 1);
+            let shrunk = &states[1];
+            let royale = shrunk.royale.unwrap();
+
+            assert_eq!((royale.min_x, royale.max_x), (1, 10));
+            assert_eq!(royale.possible, [1; 4]);
+            assert_eq!(shrunk.hazards, game.board.hazards);
+        }
+    }
+
+    #[test]
+    fn snail_mode_leaves_hazards_behind_tails() {
+        for game in load_games() {
+            let game = with_rules(&game, "standard", "snail_mode");
+            let states = play(&game, 1);
+            let (old, new) = (&states[0], &states[1]);
+
+            for snake in old.snakes.iter().filter(|s| !s.has_stacked_tail()) {
+                let cell = new.grid[*snake.tail()];
+                if !matches!(cell.cell, CellType::Snake(_)) {
+                    assert_eq!(cell.hazard, snake.len() as u8);
+                }
+            }
+            assert!(new.hazards.is_empty());
+        }
+    }
+
+    #[test]
+    fn spiral_continues_unordered_hazards() {
+        for game in load_games() {
+            let mut game = with_rules(&game, "standard", "hz_spiral");
+            let state = State::from(&game);
+            let spiral = SpiralShape {
+                center: Coord { x: 5, y: 5 },
+                first_dir: Direction::Left,
+                clockwise: false,
+            };
+            let cells: Vec<Coord> = spiral.cells(&state.grid).take(8).collect();
+
+            // the board does not list the hazards in spawn order
+            game.board.hazards = cells[..7].iter().rev().copied().collect();
+            game.turn = 3;
+
+            let states = play(&game, 3);
+            assert_eq!(states[0].spiral, Some(spiral));
+            assert_eq!(states[1].hazards, game.board.hazards);
+            assert_eq!(states[3].hazards[..7], game.board.hazards[..]);
+            assert_eq!(states[3].hazards[7], cells[7]);
+        }
+    }
+
+    #[test]
+    fn map_names_select_their_rules() {
+        let maps = [
+            ("standard", true, HazardSource::Static),
+            ("royale", true, HazardSource::Royale),
+            ("snail_mode", true, HazardSource::Snail),
+            ("hz_spiral", true, HazardSource::Scheduled),
+            ("arcade_maze", false, HazardSource::Static),
+            ("hz_islands_bridges", true, HazardSource::Static),
+            ("hz_rings", true, HazardSource::Static),
+        ];
+
+        for (name, spawns_food, hazard_source) in maps {
+            let map = map_rules(name);
+            assert_eq!(map.spawns_food(), spawns_food, "{name}");
+            assert_eq!(map.hazard_source(), hazard_source, "{name}");
+            assert_eq!(map.food_spawn_points().is_empty(), spawns_food, "{name}");
+        }
+    }
+
+    #[test]
+    fn arcade_maze_spawns_food_on_fixed_points() {
+        for game in load_games() {
+            let mut game = with_rules(&game, "standard", "arcade_maze");
+            (game.board.width, game.board.height) = (19, 21);
+            game.game.ruleset.settings.minimum_food = 5;
+            game.game.ruleset.settings.food_spawn_chance = 100;
+
+            let mut state = State::from(&game);
+            let old_food = state.food.clone();
+            state.spawn_food();
+
+            let spawned: Vec<Coord> = (0..21)
+                .flat_map(|y| (0..19).map(move |x| Coord { x, y }))
+                .filter(|pos| state.food_probability(*pos) > 0.0 && !old_food.contains(pos))
+                .collect();
+            assert!(spawned.iter().all(|pos| ARCADE_MAZE_FOOD.contains(pos)));
+
+            // one food per turn, the minimum does not apply
+            let expected: f32 = spawned.iter().map(|pos| state.food_probability(*pos)).sum();
+            assert!((expected - 1.0).abs() < 1e-3, "{expected}");
+
+            let mut sampled = State::from(&game);
+            sampled.food_spawn = FoodSpawn::Sampled;
+            sampled.spawn_food();
+            assert_eq!(sampled.food.len(), old_food.len() + 1);
+            assert!(ARCADE_MAZE_FOOD.contains(sampled.food.last().unwrap()));
+        }
+    }
+
+    #[test]
+    fn static_hazard_maps_keep_hazards() {
+        for map in ["arcade_maze", "hz_islands_bridges", "hz_rings"] {
+            for game in load_games() {
+                let mut game = with_rules(&game, "standard", map);
+                game.board.hazards = vec![Coord { x: 0, y: 0 }, Coord { x: 10, y: 10 }];
+
+                for state in play(&game, 6) {
+                    assert_eq!(state.hazards, game.board.hazards, "{map}");
+                }
+            }
+        }
+    }
+}
@@ -1,18 +1,28 @@
+mod bit_state;
 mod cell;
+mod classify;
+mod delta;
+mod food;
+mod map;
+mod move_set;
 mod outcome;
+mod royale;
+mod rules;
 mod snake;
 mod state;
+mod survival;
+mod zobrist;
 
+pub use bit_state::BitState;
 pub use cell::{CellGame, CellType};
-pub use outcome::{LossType, Outcome};
+pub use classify::MoveClass;
+pub use delta::StateDelta;
+pub use food::FoodSpawn;
+pub use map::{map_rules, MapRules, SpiralShape};
+pub use move_set::{ActionSet, JointActions, MoveSet, MAX_SNAKES};
+pub use outcome::{Elimination, LossType, Outcome};
+pub use royale::RoyaleShrink;
+pub use rules::{Growth, HazardSource, RulesConfig, Topology};
 pub use snake::Snake;
 pub use state::State;
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Mode {
-    Standard,
-    Duels,
-    Royale,
-    Constrictor,
-    Snail,
-}
+pub use survival::{Survival, SurvivalBuffers};
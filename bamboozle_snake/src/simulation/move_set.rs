@@ -0,0 +1,289 @@
+use crate::game::Direction;
+use crate::simulation::State;
+use std::ops::{Deref, DerefMut};
+
+/// Most snakes a joint action can hold
+pub const MAX_SNAKES: usize = 16;
+
+/// Moves of a snake as a 4-bit mask over the directions.
+/// An empty set only allows `Direction::None` (dead or not simulated snakes)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveSet {
+    mask: u8,
+    /// move that is tried first if it is in the set
+    preferred: Direction,
+}
+
+impl MoveSet {
+    pub const NONE: MoveSet = MoveSet {
+        mask: 0,
+        preferred: Direction::None,
+    };
+
+    #[must_use]
+    pub fn new(preferred: Direction) -> Self {
+        Self { mask: 0, preferred }
+    }
+
+    #[must_use]
+    pub fn single(dir: Direction) -> Self {
+        let mut moves = Self::new(dir);
+        moves.insert(dir);
+        moves
+    }
+
+    pub fn insert(&mut self, dir: Direction) {
+        if dir != Direction::None {
+            self.mask |= 1 << dir as u8;
+        }
+    }
+
+    #[must_use]
+    pub fn contains(&self, dir: Direction) -> bool {
+        match dir {
+            Direction::None => self.mask == 0,
+            _ => self.mask & (1 << dir as u8) != 0,
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    /// Number of moves, `Direction::None` of an empty set included
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.mask.count_ones().max(1) as usize
+    }
+
+    /// Moves in search order, the preferred move first
+    #[must_use]
+    pub fn to_array(&self) -> ([Direction; 4], usize) {
+        let mut moves = [Direction::None; 4];
+        let mut len = 0;
+
+        if self.mask == 0 {
+            return (moves, 1);
+        }
+
+        let order = [self.preferred].into_iter().chain(
+            Direction::get_alive_actions()
+                .into_iter()
+                .filter(|d| *d != self.preferred),
+        );
+
+        for dir in order.filter(|d| self.contains(*d)) {
+            moves[len] = dir;
+            len += 1;
+        }
+        (moves, len)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Direction> {
+        let (moves, len) = self.to_array();
+        moves.into_iter().take(len)
+    }
+}
+
+/// Simultaneous actions of all snakes, stored inline
+#[derive(Debug, Clone, Copy)]
+pub struct ActionSet {
+    actions: [Direction; MAX_SNAKES],
+    len: usize,
+}
+
+impl ActionSet {
+    /// Action set without any snakes
+    pub const EMPTY: ActionSet = ActionSet {
+        actions: [Direction::None; MAX_SNAKES],
+        len: 0,
+    };
+}
+
+impl Deref for ActionSet {
+    type Target = [Direction];
+
+    fn deref(&self) -> &Self::Target {
+        &self.actions[..self.len]
+    }
+}
+
+impl DerefMut for ActionSet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.actions[..self.len]
+    }
+}
+
+/// Cartesian product of the move sets of all snakes, without heap allocations.
+/// Action sets are enumerated in the order of `multi_cartesian_product`
+#[derive(Debug, Clone, Copy)]
+pub struct JointActions {
+    choices: [[Direction; 4]; MAX_SNAKES],
+    counts: [u8; MAX_SNAKES],
+    indices: [u8; MAX_SNAKES],
+    len: usize,
+    done: bool,
+}
+
+impl JointActions {
+    #[must_use]
+    pub fn new(moves: impl IntoIterator<Item = MoveSet>) -> Self {
+        let mut joint = Self {
+            choices: [[Direction::None; 4]; MAX_SNAKES],
+            counts: [0; MAX_SNAKES],
+            indices: [0; MAX_SNAKES],
+            len: 0,
+            done: false,
+        };
+
+        for move_set in moves {
+            assert!(joint.len < MAX_SNAKES, "too many snakes for a joint action");
+            let (choices, count) = move_set.to_array();
+            joint.choices[joint.len] = choices;
+            joint.counts[joint.len] = count as u8;
+            joint.len += 1;
+        }
+        joint
+    }
+
+    /// Same product with the action of one snake fixed, restarts the enumeration
+    #[must_use]
+    pub fn with_action(mut self, snake_i: usize, action: Direction) -> Self {
+        self.choices[snake_i][0] = action;
+        self.counts[snake_i] = 1;
+        self.indices = [0; MAX_SNAKES];
+        self.done = false;
+        self
+    }
+
+    /// Number of action sets of the whole product
+    #[must_use]
+    pub fn product_len(&self) -> usize {
+        self.counts[..self.len]
+            .iter()
+            .map(|c| *c as usize)
+            .product()
+    }
+}
+
+impl Iterator for JointActions {
+    type Item = ActionSet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut action_set = ActionSet {
+            actions: [Direction::None; MAX_SNAKES],
+            len: self.len,
+        };
+        for i in 0..self.len {
+            action_set.actions[i] = self.choices[i][self.indices[i] as usize];
+        }
+
+        // advance like an odometer, the last snake changes fastest
+        self.done = true;
+        for i in (0..self.len).rev() {
+            self.indices[i] += 1;
+            if self.indices[i] < self.counts[i] {
+                self.done = false;
+                break;
+            }
+            self.indices[i] = 0;
+        }
+
+        Some(action_set)
+    }
+}
+
+impl State {
+    /// Moves of the snake that do not run into walls or bodies, the last action first.
+    /// If no move is valid the snake moves up
+    #[must_use]
+    pub fn valid_moves(&self, snake_i: usize) -> MoveSet {
+        let snake = &self.snakes[snake_i];
+
+        if !snake.should_simulate || !snake.is_alive() {
+            return MoveSet::NONE;
+        }
+
+        let head = snake.head();
+        let mut moves = MoveSet::new(snake.last_action);
+
+        for dir in Direction::get_alive_actions() {
+            if self.is_valid_move(snake_i, head.step(dir)) {
+                moves.insert(dir);
+            }
+        }
+
+        if moves.is_empty() {
+            moves.insert(Direction::Up);
+        }
+        moves
+    }
+
+    /// Joint actions of all snakes
+    #[must_use]
+    pub fn joint_actions(&self) -> JointActions {
+        JointActions::new((0..self.snakes.len()).map(|i| self.valid_moves(i)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn move_set(preferred: Direction, dirs: &[Direction]) -> MoveSet {
+        let mut moves = MoveSet::new(preferred);
+        for dir in dirs {
+            moves.insert(*dir);
+        }
+        moves
+    }
+
+    #[test]
+    fn joint_actions_count_like_an_odometer() {
+        use Direction::*;
+
+        let moves = [
+            move_set(Up, &[Up, Left]),
+            MoveSet::NONE,
+            move_set(Right, &[Down, Left, Right]),
+        ];
+        let joint: Vec<Vec<Direction>> = JointActions::new(moves).map(|a| a.to_vec()).collect();
+
+        assert_eq!(
+            joint,
+            vec![
+                vec![Up, None, Right],
+                vec![Up, None, Down],
+                vec![Up, None, Left],
+                vec![Left, None, Right],
+                vec![Left, None, Down],
+                vec![Left, None, Left],
+            ]
+        );
+
+        let product: Vec<Vec<Direction>> = moves
+            .iter()
+            .map(|m| m.iter().collect::<Vec<_>>())
+            .multi_cartesian_product()
+            .collect();
+        assert_eq!(joint, product);
+        assert_eq!(JointActions::new(moves).product_len(), joint.len());
+    }
+
+    #[test]
+    fn fixed_action_restarts_the_product() {
+        use Direction::*;
+
+        let mut joint = JointActions::new([move_set(Up, &[Up, Down]), move_set(Up, &[Up, Left])]);
+        joint.next();
+
+        let fixed: Vec<Vec<Direction>> = joint.with_action(0, Down).map(|a| a.to_vec()).collect();
+        assert_eq!(fixed, vec![vec![Down, Up], vec![Down, Left]]);
+    }
+}
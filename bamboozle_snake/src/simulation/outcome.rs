@@ -1,4 +1,8 @@
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+use crate::game::GameState;
+use crate::simulation::RulesConfig;
+use serde::Serialize;
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize)]
 pub enum Outcome {
     // Possible outcomes of a game
     Loss(LossType),
@@ -7,15 +11,26 @@ pub enum Outcome {
     Win(f32),
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default, Serialize)]
 pub enum LossType {
-    // Different ways a snake can lose
+    // Different ways a snake can lose, the worst first
     #[default]
-    OwnOrWallCollision = 0,
+    WallCollision = 0,
+    SelfCollision,
     Starvation,
+    Hazard,
     SnakeCollision,
     HeadCollision,
-    None,
+}
+
+/// Why, when and by whom a snake was eliminated
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Elimination {
+    pub cause: LossType,
+    /// first turn the snake is no longer on the board
+    pub turn: u32,
+    /// snake whose body or head the eliminated snake ran into
+    pub by: Option<u8>,
 }
 
 impl Outcome {
@@ -24,14 +39,59 @@ impl Outcome {
         match self {
             Outcome::Win(score) => 1_000_000.0 + score,
             Outcome::Loss(reason) => match reason {
-                LossType::OwnOrWallCollision => -1_000_010.0,
+                LossType::WallCollision => -1_000_010.0,
+                LossType::SelfCollision => -1_000_009.0,
                 LossType::Starvation => -1_000_008.0,
+                LossType::Hazard => -1_000_007.0,
                 LossType::SnakeCollision => -1_000_006.0,
                 LossType::HeadCollision => -1_000_004.0,
-                LossType::None => todo!(),
             },
             Outcome::Draw => -1000.0,
             Outcome::Heuristic(score) => *score,
         }
     }
 }
+
+impl Elimination {
+    /// Reconstruct the elimination of our snake from the final board of a game.
+    /// Snake indices are the ones of `State`, collisions with snakes that were
+    /// eliminated in the same turn can not be recovered
+    #[must_use]
+    pub fn infer(game_state: &GameState) -> Option<Elimination> {
+        let you = &game_state.you;
+        let board = &game_state.board;
+
+        if board.snakes.iter().any(|s| s == you) {
+            return None;
+        }
+
+        let head = you.body[0];
+        let enemies = || board.snakes.iter().filter(|s| *s != you).enumerate();
+        let on_board = head.x >= 0
+            && head.y >= 0
+            && head.x < board.width as i32
+            && head.y < board.height as i32;
+
+        let (cause, by) = if you.health == 0 && board.hazards.contains(&head) {
+            (LossType::Hazard, None)
+        } else if you.health == 0 {
+            (LossType::Starvation, None)
+        } else if !on_board && !RulesConfig::from(game_state).is_wrapped() {
+            (LossType::WallCollision, None)
+        } else if you.body[1..].contains(&head) {
+            (LossType::SelfCollision, None)
+        } else if let Some((i, _)) = enemies().find(|(_, s)| s.body[0] == head) {
+            (LossType::HeadCollision, Some(i as u8 + 1))
+        } else if let Some((i, _)) = enemies().find(|(_, s)| s.body[1..].contains(&head)) {
+            (LossType::SnakeCollision, Some(i as u8 + 1))
+        } else {
+            return None;
+        };
+
+        Some(Elimination {
+            cause,
+            turn: game_state.turn,
+            by,
+        })
+    }
+}
@@ -0,0 +1,148 @@
+use crate::game::{Coord, Settings};
+use crate::grid::Grid;
+use crate::simulation::CellGame;
+
+const LEFT: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const TOP: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoyaleShrink {
+    /// Area that is certainly not covered by the royale hazards
+    pub min_x: i32,
+    pub max_x: i32,
+    pub min_y: i32,
+    pub max_y: i32,
+    /// Rows/ columns per side (left, right, bottom, top) that might have turned into hazards
+    pub possible: [i32; 4],
+}
+
+impl RoyaleShrink {
+    /// Derive the safe area from the hazards of the current board
+    #[must_use]
+    pub fn from_grid(grid: &Grid<CellGame>) -> Self {
+        let mut shrink = Self {
+            min_x: grid.width as i32 - 1,
+            max_x: 0,
+            min_y: grid.height as i32 - 1,
+            max_y: 0,
+            possible: [0; 4],
+        };
+
+        for y in 0..grid.height as i32 {
+            for x in 0..grid.width as i32 {
+                if grid[Coord { x, y }].hazard == 0 {
+                    shrink.min_x = shrink.min_x.min(x);
+                    shrink.max_x = shrink.max_x.max(x);
+                    shrink.min_y = shrink.min_y.min(y);
+                    shrink.max_y = shrink.max_y.max(y);
+                }
+            }
+        }
+        shrink
+    }
+
+    #[must_use]
+    pub fn is_shrink_turn(turn: u32, settings: &Settings) -> bool {
+        let every_n_turns = settings.royale.shrink_every_n_turns;
+        every_n_turns > 0 && turn >= every_n_turns && turn.is_multiple_of(every_n_turns)
+    }
+
+    /// A side is exhausted once its possible hazards reach the far edge of the safe area
+    fn can_shrink(&self, side: usize) -> bool {
+        match side {
+            LEFT => self.min_x + self.possible[LEFT] < self.max_x,
+            RIGHT => self.max_x - self.possible[RIGHT] > self.min_x,
+            BOTTOM => self.min_y + self.possible[BOTTOM] < self.max_y,
+            _ => self.max_y - self.possible[TOP] > self.min_y,
+        }
+    }
+
+    /// Shrink the area for a new turn. The engine picks one of the sides at random and the
+    /// hazards only show on the next board, so every open side is just possible: opposite
+    /// sides stay open together and there is never a single side left to be certain about
+    pub fn step(&mut self, turn: u32, settings: &Settings) {
+        if !RoyaleShrink::is_shrink_turn(turn, settings) {
+            return;
+        }
+
+        for side in 0..4 {
+            if self.can_shrink(side) {
+                self.possible[side] += 1;
+            }
+        }
+    }
+
+    /// Cell inside the safe area that may already be covered by hazards
+    #[must_use]
+    pub fn is_possible_hazard(&self, pos: Coord) -> bool {
+        if pos.x < self.min_x || pos.x > self.max_x || pos.y < self.min_y || pos.y > self.max_y {
+            return false;
+        }
+
+        pos.x < self.min_x + self.possible[LEFT]
+            || pos.x > self.max_x - self.possible[RIGHT]
+            || pos.y < self.min_y + self.possible[BOTTOM]
+            || pos.y > self.max_y - self.possible[TOP]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shrink(size: i32) -> RoyaleShrink {
+        RoyaleShrink {
+            min_x: 0,
+            max_x: size - 1,
+            min_y: 0,
+            max_y: size - 1,
+            possible: [0; 4],
+        }
+    }
+
+    fn settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.royale.shrink_every_n_turns = 5;
+        settings
+    }
+
+    #[test]
+    fn shrinks_every_n_turns() {
+        let mut settings = settings();
+        assert!(!RoyaleShrink::is_shrink_turn(0, &settings));
+        assert!(!RoyaleShrink::is_shrink_turn(7, &settings));
+        assert!(RoyaleShrink::is_shrink_turn(5, &settings));
+        assert!(RoyaleShrink::is_shrink_turn(10, &settings));
+
+        settings.royale.shrink_every_n_turns = 0;
+        assert!(!RoyaleShrink::is_shrink_turn(10, &settings));
+    }
+
+    #[test]
+    fn open_sides_are_only_possible() {
+        let mut royale = shrink(11);
+        royale.step(4, &settings());
+        assert_eq!(royale, shrink(11));
+
+        royale.step(5, &settings());
+        assert_eq!(royale.possible, [1; 4]);
+        assert!(royale.is_possible_hazard(Coord { x: 0, y: 5 }));
+        assert!(!royale.is_possible_hazard(Coord { x: 5, y: 5 }));
+    }
+
+    #[test]
+    fn opposite_sides_are_exhausted_together() {
+        let mut royale = shrink(5);
+        for turn in (5..=30).step_by(5) {
+            royale.step(turn, &settings());
+            assert_eq!(royale.possible[LEFT], royale.possible[RIGHT]);
+            assert_eq!(royale.possible[BOTTOM], royale.possible[TOP]);
+        }
+
+        // every side may have covered all but the far column or row
+        assert_eq!(royale.possible, [4; 4]);
+        assert_eq!((royale.min_x, royale.max_x), (0, 4));
+    }
+}
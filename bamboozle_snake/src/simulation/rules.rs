@@ -0,0 +1,100 @@
+use crate::game::{GameState, Settings};
+use crate::simulation::{map_rules, MapRules};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    Bounded,
+    Wrapped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Growth {
+    /// Tail follows the head, snakes grow by eating
+    Standard,
+    /// Snakes grow every turn and never starve
+    Constrictor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HazardSource {
+    /// Hazards stay where the board puts them
+    Static,
+    /// Royale ring closing in from the edges
+    Royale,
+    /// Tails leave decaying hazards behind
+    Snail,
+    /// Hazards spawn on a fixed schedule
+    Scheduled,
+}
+
+/// Independent rules of the game that is played
+#[derive(Debug, Clone, Copy)]
+pub struct RulesConfig {
+    pub topology: Topology,
+    pub growth: Growth,
+    pub hazards: HazardSource,
+    pub map: &'static dyn MapRules,
+    pub squad: bool,
+    pub solo: bool,
+    pub settings: Settings,
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        let map = map_rules("standard");
+
+        Self {
+            topology: Topology::Bounded,
+            growth: Growth::Standard,
+            hazards: map.hazard_source(),
+            map,
+            squad: false,
+            solo: false,
+            settings: Settings::default(),
+        }
+    }
+}
+
+impl From<&GameState> for RulesConfig {
+    fn from(game_state: &GameState) -> Self {
+        let ruleset = &game_state.game.ruleset;
+        let name = ruleset.name.as_str();
+
+        // the royale ruleset brings its own map
+        let map = if name.contains("royale") {
+            map_rules("royale")
+        } else {
+            map_rules(&game_state.game.map)
+        };
+
+        Self {
+            topology: if name.contains("wrapped") {
+                Topology::Wrapped
+            } else {
+                Topology::Bounded
+            },
+            growth: if name.contains("constrictor") {
+                Growth::Constrictor
+            } else {
+                Growth::Standard
+            },
+            hazards: map.hazard_source(),
+            map,
+            squad: name.contains("squad"),
+            solo: name == "solo" || game_state.board.snakes.len() == 1,
+            settings: ruleset.settings,
+        }
+    }
+}
+
+impl RulesConfig {
+    #[must_use]
+    pub fn is_wrapped(&self) -> bool {
+        self.topology == Topology::Wrapped
+    }
+
+    #[must_use]
+    pub fn is_constrictor(&self) -> bool {
+        self.growth == Growth::Constrictor
+    }
+}
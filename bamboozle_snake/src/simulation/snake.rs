@@ -1,23 +1,28 @@
 use crate::game::{Battlesnake, Coord, Direction};
-use crate::simulation::outcome::LossType;
+use crate::simulation::outcome::{Elimination, LossType};
+use std::collections::vec_deque;
+use std::collections::VecDeque;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Snake {
     pub health: i16,
-    pub body: Vec<Coord>,
+    /// head at the front, tail at the back (stacked after eating)
+    body: VecDeque<Coord>,
     pub last_action: Direction,
     pub should_simulate: bool,
-    pub loss_reason: LossType,
+    pub elimination: Option<Elimination>,
+    pub squad: Option<u8>,
 }
 
 impl From<&Battlesnake> for Snake {
     fn from(snake: &Battlesnake) -> Self {
         Self {
             health: snake.health as i16,
-            body: snake.body.clone(),
+            body: snake.body.iter().copied().collect(),
             last_action: Direction::None,
             should_simulate: true,
-            loss_reason: LossType::None,
+            elimination: None,
+            squad: None,
         }
     }
 }
@@ -26,10 +31,11 @@ impl Snake {
     pub fn new(health: i16, body: Vec<Coord>, last_action: Direction) -> Self {
         Self {
             health,
-            body,
+            body: body.into(),
             last_action,
             should_simulate: true,
-            loss_reason: LossType::None,
+            elimination: None,
+            squad: None,
         }
     }
 
@@ -37,53 +43,82 @@ impl Snake {
         self.body[0]
     }
 
+    pub fn head_mut(&mut self) -> &mut Coord {
+        self.body.front_mut().unwrap()
+    }
+
+    /// Body part behind the head
+    pub fn neck(&self) -> Option<Coord> {
+        self.body.get(1).copied()
+    }
+
     pub fn tail(&self) -> &Coord {
-        self.body.last().unwrap()
+        self.body.back().unwrap()
+    }
+
+    /// Body from the head to the tail
+    pub fn body(&self) -> vec_deque::Iter<'_, Coord> {
+        self.body.iter()
+    }
+
+    /// Tail stays in place next turn because the snake just grew
+    pub fn has_stacked_tail(&self) -> bool {
+        let mut from_tail = self.body.iter().rev();
+        self.len() > 1 && from_tail.next() == from_tail.next()
     }
 
     pub fn is_alive(&self) -> bool {
-        self.loss_reason == LossType::None
+        self.elimination.is_none()
+    }
+
+    /// Cause of the elimination (the default for alive snakes)
+    pub fn loss_reason(&self) -> LossType {
+        self.elimination.map_or(LossType::default(), |e| e.cause)
     }
 
-    pub fn die(&mut self, reason: LossType) {
-        self.loss_reason = reason;
+    /// Eliminate the snake in `turn`, `by` is the snake it ran into
+    pub fn die(&mut self, cause: LossType, turn: u32, by: Option<usize>) {
+        self.elimination = Some(Elimination {
+            cause,
+            turn,
+            by: by.map(|i| i as u8),
+        });
     }
 
     pub fn eat(&mut self) {
         self.health = 100;
-        self.body.push(*self.tail());
+        self.grow();
+    }
+
+    /// Stack another segment onto the tail
+    pub fn grow(&mut self) {
+        self.body.push_back(*self.tail());
     }
 
     pub fn len(&self) -> usize {
         self.body.len()
     }
 
-    #[must_use]
-    pub fn step(&self, action: Direction) -> Self {
+    /// Move the snake in place, the tail follows the head
+    pub fn step(&mut self, action: Direction) {
         let new_head = self.head().step(action);
-        let used_body_len = self.body.len() - 1; // drop tail
-        let new_body = [&[new_head], &self.body[0..used_body_len]].concat();
-
-        Self {
-            health: self.health - 1,
-            body: new_body,
-            last_action: action,
-            should_simulate: self.should_simulate,
-            loss_reason: self.loss_reason,
-        }
+        self.body.pop_back();
+        self.body.push_front(new_head);
+        self.health -= 1;
+        self.last_action = action;
     }
 
-    #[must_use]
-    pub fn step_constrictor(&self, action: Direction) -> Self {
-        let new_head = self.head().step(action);
-        let new_body = [&[new_head], &self.body[..]].concat();
+    /// Move the snake in place, constrictor snakes grow every turn
+    pub fn step_constrictor(&mut self, action: Direction) {
+        self.body.push_front(self.head().step(action));
+        self.health = 100;
+        self.last_action = action;
+    }
 
-        Self {
-            health: 100,
-            body: new_body,
-            last_action: action,
-            should_simulate: self.should_simulate,
-            loss_reason: self.loss_reason,
-        }
+    /// Revert the body of a step, `len` and `tail` are the values before the step
+    pub fn step_back(&mut self, len: usize, tail: Coord) {
+        self.body.pop_front();
+        self.body.truncate(len - 1);
+        self.body.push_back(tail);
     }
 }
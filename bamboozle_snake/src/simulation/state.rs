@@ -1,13 +1,14 @@
 use crate::game::{Coord, Direction, GameState};
 use crate::grid::Grid;
 use crate::simulation::outcome::LossType;
-use crate::simulation::{CellGame, CellType, Mode, Snake};
+use crate::simulation::zobrist::SnakeKey;
+use crate::simulation::{
+    CellGame, CellType, FoodSpawn, RoyaleShrink, RulesConfig, Snake, SpiralShape,
+};
 use log::debug;
 use std::cmp::Ordering;
 use std::iter::zip;
 
-const HAZARD_DAMAGE: i16 = 14;
-
 #[derive(Debug, Clone)]
 pub struct State {
     pub turn: u32,
@@ -15,7 +16,14 @@ pub struct State {
     pub food: Vec<Coord>,
     pub hazards: Vec<Coord>,
     pub grid: Grid<CellGame>,
-    pub mode: Mode,
+    pub config: RulesConfig,
+    pub food_spawn: FoodSpawn,
+    /// probability of spawned food on each free cell, reset when a snake eats
+    pub food_chance: f32,
+    pub royale: Option<RoyaleShrink>,
+    pub spiral: Option<SpiralShape>,
+    /// Zobrist hash of the position, updated with every step
+    pub hash: u64,
 }
 
 impl From<&GameState> for State {
@@ -35,12 +43,19 @@ impl From<&GameState> for State {
 
         snakes.append(&mut filtered_snakes);
 
-        let wrapped = game_state.game.ruleset.name.contains("wrapped");
-        let mode = State::determine_mode(game_state);
+        let config = RulesConfig::from(game_state);
 
-        debug!("State detected mode {:?}", mode);
+        debug!("State detected rules {:?}", config);
 
-        let grid = Grid::new(board.width as usize, board.height as usize, wrapped);
+        if config.squad {
+            State::assign_squads(&mut snakes, game_state);
+        }
+
+        let grid = Grid::new(
+            board.width as usize,
+            board.height as usize,
+            config.is_wrapped(),
+        );
 
         let mut state = Self {
             turn: game_state.turn,
@@ -48,9 +63,16 @@ impl From<&GameState> for State {
             food: board.food.clone(),
             hazards: board.hazards.clone(),
             grid,
-            mode,
+            config,
+            food_spawn: FoodSpawn::default(),
+            food_chance: 0.0,
+            royale: None,
+            spiral: None,
+            hash: 0,
         };
         state.fill_grid();
+        config.map.init(&mut state);
+        state.hash = state.compute_hash();
 
         state
     }
@@ -65,10 +87,9 @@ impl State {
         hazards: Vec<Coord>,
         width: usize,
         height: usize,
-        wrapped: bool,
-        mode: Mode,
+        config: RulesConfig,
     ) -> Self {
-        let grid = Grid::new(width, height, wrapped);
+        let grid = Grid::new(width, height, config.is_wrapped());
 
         Self {
             turn,
@@ -76,24 +97,54 @@ impl State {
             food,
             hazards,
             grid,
-            mode,
+            config,
+            food_spawn: FoodSpawn::default(),
+            food_chance: 0.0,
+            royale: None,
+            spiral: None,
+            hash: 0,
         }
     }
 
-    pub fn determine_mode(game_state: &GameState) -> Mode {
-        if game_state.game.ruleset.name == "constrictor" {
-            Mode::Constrictor
-        } else if game_state.game.map == "snail_mode" {
-            Mode::Snail
-        } else if game_state.game.map == "royale" {
-            Mode::Royale
-        } else if game_state.board.snakes.len() == 2 {
-            Mode::Duels
-        } else {
-            Mode::Standard
+    fn assign_squads(snakes: &mut [Snake], game_state: &GameState) {
+        let you = &game_state.you;
+        let squad_names = std::iter::once(&you.squad).chain(
+            game_state
+                .board
+                .snakes
+                .iter()
+                .filter(|s| s.id != you.id)
+                .map(|s| &s.squad),
+        );
+
+        let mut squads: Vec<&String> = Vec::new();
+        for (snake, name) in zip(snakes.iter_mut(), squad_names) {
+            if name.is_empty() {
+                continue;
+            }
+
+            let squad = squads.iter().position(|s| *s == name).unwrap_or_else(|| {
+                squads.push(name);
+                squads.len() - 1
+            });
+            snake.squad = Some(squad as u8);
         }
     }
 
+    /// Snakes of the same squad (a snake is not its own ally)
+    #[must_use]
+    pub fn is_ally(&self, snake_i: usize, other_i: usize) -> bool {
+        snake_i != other_i
+            && self.snakes[snake_i].squad.is_some()
+            && self.snakes[snake_i].squad == self.snakes[other_i].squad
+    }
+
+    /// Snake is on the same team as our snake (index 0)
+    #[must_use]
+    pub fn is_our_team(&self, snake_i: usize) -> bool {
+        snake_i == 0 || self.is_ally(0, snake_i)
+    }
+
     pub fn fill_grid(&mut self) {
         self.grid.fill(&self.snakes, &self.food, &self.hazards);
     }
@@ -105,7 +156,18 @@ impl State {
 
     #[must_use]
     pub fn is_end_state(&self) -> bool {
-        self.snakes.iter().filter(|s| s.is_alive()).count() <= 1
+        // solo games only end with our death
+        if self.config.solo {
+            return !self.snakes[0].is_alive();
+        }
+
+        let mut alive = self.snakes.iter().enumerate().filter(|(_, s)| s.is_alive());
+
+        // the game ends if only one snake or squad is left
+        match alive.next() {
+            None => true,
+            Some((first_i, _)) => alive.all(|(i, _)| self.is_ally(first_i, i)),
+        }
     }
 
     #[must_use]
@@ -120,45 +182,40 @@ impl State {
         -1
     }
 
-    pub fn kill_starved(&mut self) {
-        for snake in self.snakes.iter_mut() {
-            if snake.health == 0 {
-                snake.die(LossType::Starvation);
-            }
-        }
+    /// Cell that might be covered by royale hazards (but it is not certain yet)
+    #[must_use]
+    pub fn is_possible_hazard(&self, pos: Coord) -> bool {
+        self.royale.is_some_and(|r| r.is_possible_hazard(pos))
     }
 
     pub fn get_valid_actions(&self, snake_i: usize) -> Vec<Direction> {
-        let snake = &self.snakes[snake_i];
+        self.valid_moves(snake_i).iter().collect()
+    }
 
-        if !snake.should_simulate || !snake.is_alive() {
-            return vec![Direction::None];
+    /// Position is not blocked for the snake (squads may move through their allies)
+    #[must_use]
+    pub fn is_valid_move(&self, snake_i: usize, pos: Coord) -> bool {
+        if !self.grid.contains(pos) {
+            return false;
         }
 
-        let head = snake.head();
-
-        let mut valid_actions = Vec::with_capacity(4);
-
-        if snake.last_action != Direction::None
-            && self.grid.is_valid_pos(head.step(snake.last_action))
-        {
-            valid_actions.push(snake.last_action);
+        match self.grid[pos].cell {
+            CellType::Snake(si) => {
+                self.config.settings.squad.allow_body_collisions
+                    && self.is_ally(snake_i, si as usize)
+            }
+            _ => true,
         }
+    }
 
-        valid_actions.extend(
-            Direction::get_alive_actions()
-                .into_iter()
-                .filter(|d| *d != snake.last_action && self.grid.is_valid_pos(head.step(*d))),
-        );
-
-        if valid_actions.is_empty() {
-            valid_actions.push(Direction::Up)
+    /// Keep heads on the board in wrapped games, so they compare equal to food
+    pub(crate) fn wrap_head(&self, snake: &mut Snake) {
+        if self.grid.wrapped {
+            self.grid.wrap_around(snake.head_mut());
         }
-
-        valid_actions
     }
 
-    fn check_head_collisions(&self, snakes: &mut Vec<Snake>) {
+    pub(crate) fn check_head_collisions(snakes: &mut [Snake], turn: u32) {
         for s1_i in 0..snakes.len() - 1 {
             if !snakes[s1_i].is_alive() {
                 continue;
@@ -170,11 +227,15 @@ impl State {
                     let s2_len = snakes[s2_i].len();
 
                     match s1_len.cmp(&s2_len) {
-                        Ordering::Less => snakes[s1_i].die(LossType::HeadCollision),
-                        Ordering::Greater => snakes[s2_i].die(LossType::HeadCollision),
+                        Ordering::Less => {
+                            snakes[s1_i].die(LossType::HeadCollision, turn, Some(s2_i))
+                        }
+                        Ordering::Greater => {
+                            snakes[s2_i].die(LossType::HeadCollision, turn, Some(s1_i))
+                        }
                         Ordering::Equal => {
-                            snakes[s1_i].die(LossType::HeadCollision);
-                            snakes[s2_i].die(LossType::HeadCollision);
+                            snakes[s1_i].die(LossType::HeadCollision, turn, Some(s2_i));
+                            snakes[s2_i].die(LossType::HeadCollision, turn, Some(s1_i));
                         }
                     }
                 }
@@ -182,33 +243,77 @@ impl State {
         }
     }
 
-    fn check_collisions(&self, snakes: &mut Vec<Snake>) {
-        for (i, snake) in snakes.iter_mut().enumerate() {
+    pub(crate) fn check_collisions(&self, snakes: &mut [Snake]) {
+        for i in 0..snakes.len() {
+            let snake = &snakes[i];
+
             // ignore not simulated and dead snakes
-            if snake.body[0] == snake.body[1] || !snake.is_alive() {
+            if snake.neck() == Some(snake.head()) || !snake.is_alive() {
                 continue;
             }
 
             let head = snake.head();
-            // if snake.is_alive() && (!self.grid.contains(head) || self.grid.is_snake(head)) {
-            //     // probably not all collisions are own/ wall collisions but should not matter
-            //     snake.die(LossType::OwnOrWallCollision);
-            // }
-            if !self.grid.contains(head)
-                || matches!(self.grid[head].cell, CellType::Snake(si) if si == i as u8)
-            {
-                snake.die(LossType::OwnOrWallCollision);
-            } else if self.grid.is_snake(head) {
-                snake.die(LossType::SnakeCollision);
+            let turn = self.turn + 1;
+
+            if !self.grid.contains(head) {
+                snakes[i].die(LossType::WallCollision, turn, None);
+            } else if let CellType::Snake(si) = self.grid[head].cell {
+                let ally_body = self.config.settings.squad.allow_body_collisions
+                    && snake.squad.is_some()
+                    && snake.squad == snakes[si as usize].squad;
+
+                if si == i as u8 {
+                    snakes[i].die(LossType::SelfCollision, turn, None);
+                } else if !ally_body {
+                    snakes[i].die(LossType::SnakeCollision, turn, Some(si as usize));
+                }
+            }
+        }
+    }
+
+    /// Share length, health and eliminations within squads
+    pub(crate) fn apply_squad_rules(&mut self) {
+        let squad_settings = self.config.settings.squad;
+
+        for squad in 0..self.snakes.len() as u8 {
+            let members = || self.snakes.iter().filter(move |s| s.squad == Some(squad));
+
+            if members().next().is_none() {
+                break;
+            }
+
+            let max_health = members().filter(|s| s.is_alive()).map(|s| s.health).max();
+            let max_length = members().filter(|s| s.is_alive()).map(|s| s.len()).max();
+            let eliminated = members().find_map(|s| s.elimination);
+
+            for snake in self.snakes.iter_mut().filter(|s| s.squad == Some(squad)) {
+                if !snake.is_alive() {
+                    continue;
+                }
+
+                if squad_settings.shared_elimination {
+                    if let Some(elimination) = eliminated {
+                        snake.elimination = Some(elimination);
+                        continue;
+                    }
+                }
+                if squad_settings.shared_health {
+                    snake.health = max_health.unwrap_or(snake.health);
+                }
+                if squad_settings.shared_length {
+                    while snake.len() < max_length.unwrap_or(0) {
+                        snake.grow();
+                    }
+                }
             }
         }
     }
 
     #[must_use]
-    pub fn step(&self, actions: &Vec<Direction>) -> Self {
+    pub fn step(&self, actions: &[Direction]) -> Self {
         debug_assert!(actions.len() == self.snakes.len());
 
-        if self.mode == Mode::Constrictor {
+        if self.config.is_constrictor() {
             return self.step_constrictor(actions);
         }
 
@@ -218,30 +323,48 @@ impl State {
         // create new moved snakes and apply actions
         for (snake, action) in zip(&self.snakes, actions) {
             if snake.is_alive() {
-                new_snakes.push(snake.step(*action));
+                let mut new_snake = snake.clone();
+                new_snake.step(*action);
+                self.wrap_head(&mut new_snake);
+                new_snakes.push(new_snake);
             } else {
                 new_snakes.push(snake.clone());
             }
         }
 
         //check head collisions
-        self.check_head_collisions(&mut new_snakes);
+        State::check_head_collisions(&mut new_snakes, self.turn + 1);
 
         // check collisions
         self.check_collisions(&mut new_snakes);
 
+        let mut hash = self.hash;
+        let mut eaten = false;
+
         // check hazards and food
         for snake in new_snakes.iter_mut().filter(|s| s.is_alive()) {
-            snake.health -= HAZARD_DAMAGE * self.grid[snake.head()].hazard as i16;
+            let hazard = self.grid[snake.head()].hazard;
+            snake.health -= self.config.settings.hazard_damage_per_turn * hazard as i16;
 
             if self.grid.is_food(snake.head()) {
                 snake.eat();
+                eaten = true;
 
                 // delete eaten food
-                new_food.retain(|&f| f != snake.head());
+                new_food.retain(|&f| {
+                    if f == snake.head() {
+                        hash ^= self.food_key(f);
+                    }
+                    f != snake.head()
+                });
             }
-            if snake.health < 0 {
-                snake.die(LossType::Starvation);
+            if snake.health <= 0 {
+                let cause = if hazard > 0 {
+                    LossType::Hazard
+                } else {
+                    LossType::Starvation
+                };
+                snake.die(cause, self.turn + 1, None);
             }
         }
 
@@ -252,34 +375,44 @@ impl State {
             self.hazards.clone(),
             self.grid.width,
             self.grid.height,
-            self.grid.wrapped,
-            self.mode,
+            self.config,
         );
 
-        new_state.kill_starved();
+        new_state.apply_squad_rules();
+        new_state.fill_grid();
+        new_state.hash = hash
+            ^ self.snakes_hash_delta(&new_state.snakes, true)
+            ^ self.phase_key()
+            ^ new_state.phase_key();
 
-        if new_state.mode == Mode::Snail {
-            new_state.apply_snail_mode(self);
-        } else {
-            new_state.fill_grid();
-        }
+        new_state.royale = self.royale;
+        new_state.spiral = self.spiral;
+        self.config.map.update_from(&mut new_state, self);
+
+        new_state.food_spawn = self.food_spawn;
+        // expected food is only tracked until the next food is eaten
+        new_state.food_chance = if eaten { 0.0 } else { self.food_chance };
+        new_state.spawn_food();
 
         new_state
     }
 
-    fn step_constrictor(&self, actions: &Vec<Direction>) -> Self {
+    fn step_constrictor(&self, actions: &[Direction]) -> Self {
         let mut new_snakes: Vec<Snake> = Vec::with_capacity(self.snakes.len());
 
         for (snake, action) in zip(&self.snakes, actions) {
             if snake.is_alive() {
-                new_snakes.push(snake.step_constrictor(*action));
+                let mut new_snake = snake.clone();
+                new_snake.step_constrictor(*action);
+                self.wrap_head(&mut new_snake);
+                new_snakes.push(new_snake);
             } else {
                 new_snakes.push(snake.clone());
             }
         }
 
         //check head collisions
-        self.check_head_collisions(&mut new_snakes);
+        State::check_head_collisions(&mut new_snakes, self.turn + 1);
 
         // check collisions
         self.check_collisions(&mut new_snakes);
@@ -291,37 +424,27 @@ impl State {
             Vec::new(),
             self.grid.width,
             self.grid.height,
-            self.grid.wrapped,
-            self.mode,
+            self.config,
         );
 
         new_state.fill_grid();
+        // food, hazards and the royale area are dropped
+        new_state.hash = self.hash
+            ^ self.items_hash()
+            ^ State::royale_key(&self.royale)
+            ^ self.snakes_hash_delta(&new_state.snakes, false)
+            ^ self.phase_key()
+            ^ new_state.phase_key();
         new_state
     }
 
-    fn apply_snail_mode(&mut self, old_state: &State) {
-
-        // dont use the hazards vector in snail mode
-        self.hazards.clear();
-        self.fill_grid();
-
-        // set hazards to reduced old value
-        for (old_cell, new_cell) in zip(old_state.grid.cells.iter(), self.grid.cells.iter_mut()) {
-            if old_cell.hazard > 1 {
-                new_cell.hazard = old_cell.hazard.saturating_sub(1);
-            }
-        }
-
-        // add new hazards
-        for old_snake in old_state.snakes.iter().filter(|s| s.is_alive()) {
-
-            // hazards spawn only if tail "disappeared" (snake did not eat food)
-            if *old_snake.tail() != old_snake.body[old_snake.body.len() - 2] {
-                let cell = &mut self.grid[*old_snake.tail()];
-                if !matches!(cell.cell, CellType::Snake(_)) {
-                    cell.hazard = old_snake.len() as u8;
-                }
-            }
-        }
+    /// Hash change of all snakes that were alive before the step
+    pub(crate) fn snakes_hash_delta(&self, new_snakes: &[Snake], popped: bool) -> u64 {
+        zip(&self.snakes, new_snakes)
+            .enumerate()
+            .filter(|(_, (old, _))| old.is_alive())
+            .fold(0, |acc, (i, (old, new))| {
+                acc ^ self.snake_hash_delta(i, &SnakeKey::from(old), new, popped)
+            })
     }
 }
@@ -0,0 +1,230 @@
+use crate::game::Coord;
+use crate::grid::Grid;
+use crate::heuristic::{ChamberBuffers, Chambers};
+use crate::simulation::State;
+use std::collections::VecDeque;
+
+/// Whether a snake can keep moving in the space it has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Survival {
+    /// a body cell is reachable after it frees up, the snake can follow that body
+    pub safe_loop: bool,
+    /// the safe loop follows the own body, it does not depend on the other snakes
+    pub own_tail: bool,
+    /// turns the snake can surely move, the longest path through the cells it reaches
+    /// without passing a body. `u16::MAX` if it can follow its own tail forever
+    pub turns: u16,
+}
+
+impl Survival {
+    /// 1.0 if the snake can follow its own tail. Otherwise the share of its length it can
+    /// surely move, a loop behind other bodies depends on their moves and makes up half of
+    /// the rest
+    #[must_use]
+    pub fn score(&self, length: usize) -> f32 {
+        let space = (self.turns as f32 / length.max(1) as f32).min(1.0);
+
+        if self.own_tail {
+            1.0
+        } else if self.safe_loop {
+            0.5 + 0.5 * space
+        } else {
+            space
+        }
+    }
+}
+
+/// Grids of the body timing, the search and the path estimate, reused by `State::survival_in`
+#[derive(Debug, Clone, Default)]
+pub struct SurvivalBuffers {
+    free_at: Grid<u16>,
+    owner: Grid<u8>,
+    visited: Grid<bool>,
+    queue: VecDeque<(Coord, u32)>,
+    chambers: Chambers,
+    chamber_buffers: ChamberBuffers,
+}
+
+impl State {
+    #[must_use]
+    pub fn survival(&self, snake_i: usize) -> Survival {
+        self.survival_in(snake_i, &mut SurvivalBuffers::default())
+    }
+
+    /// Like `survival`, but searches in the buffers of an earlier call
+    #[must_use]
+    pub fn survival_in(&self, snake_i: usize, buffers: &mut SurvivalBuffers) -> Survival {
+        self.survival_from_in(snake_i, self.snakes[snake_i].head(), 0, buffers)
+    }
+
+    /// Reachability from `start` after `elapsed` turns, body cells free up when their tail
+    /// has passed and close a loop, the space behind them is not expanded. Other snakes might
+    /// eat once, so their bodies need one turn of margin, food on our own path is not taken
+    /// into account. Without a loop the snake can move as long as its longest path through
+    /// the reached cells.
+    #[must_use]
+    pub fn survival_from(&self, snake_i: usize, start: Coord, elapsed: u32) -> Survival {
+        self.survival_from_in(snake_i, start, elapsed, &mut SurvivalBuffers::default())
+    }
+
+    fn survival_from_in(
+        &self,
+        snake_i: usize,
+        start: Coord,
+        elapsed: u32,
+        buffers: &mut SurvivalBuffers,
+    ) -> Survival {
+        let SurvivalBuffers {
+            free_at,
+            owner,
+            visited,
+            queue,
+            chambers,
+            chamber_buffers,
+        } = buffers;
+        self.body_timing(snake_i, free_at, owner);
+
+        let mut survival = Survival::default();
+        visited.reset(self.grid.width, self.grid.height, self.grid.wrapped, false);
+        visited[start] = true;
+
+        queue.clear();
+        queue.push_back((start, elapsed));
+
+        while let Some((pos, step)) = queue.pop_front() {
+            for mut next in pos.get_neighbours() {
+                if !self.grid.contains(next) {
+                    continue;
+                }
+                if self.grid.wrapped {
+                    self.grid.wrap_around(&mut next);
+                }
+                if visited[next] {
+                    continue;
+                }
+
+                let step = step + 1;
+                let free_at = free_at[next] as u32;
+                if free_at > 0 {
+                    let own = owner[next] as usize == snake_i;
+                    let margin = if own { 0 } else { 1 };
+                    if step >= free_at + margin {
+                        survival.safe_loop = true;
+                        survival.own_tail |= own;
+                    }
+                    // the space behind a body depends on when it is passed
+                    continue;
+                }
+
+                visited[next] = true;
+                queue.push_back((next, step));
+            }
+        }
+
+        survival.turns = if survival.own_tail {
+            u16::MAX
+        } else {
+            chambers.analyse_in(visited, start, |visited| *visited, chamber_buffers);
+            chambers.max_path().min(u16::MAX as usize) as u16
+        };
+        survival
+    }
+
+    /// Fill in the turn every body cell frees up (0 for free cells) and the snake it belongs
+    /// to, ally bodies are left out if the snake may pass them
+    pub(crate) fn body_timing(
+        &self,
+        snake_i: usize,
+        free_at: &mut Grid<u16>,
+        owner: &mut Grid<u8>,
+    ) {
+        let (width, height, wrapped) = (self.grid.width, self.grid.height, self.grid.wrapped);
+        free_at.reset(width, height, wrapped, 0);
+        owner.reset(width, height, wrapped, 0);
+
+        for (i, snake) in self.snakes.iter().enumerate().filter(|(_, s)| s.is_alive()) {
+            let passable = i != snake_i
+                && self.config.settings.squad.allow_body_collisions
+                && self.is_ally(snake_i, i);
+            if passable {
+                continue;
+            }
+
+            for (tail_dist, pos) in snake.body().rev().enumerate() {
+                free_at[*pos] = tail_dist as u16 + 1;
+                owner[*pos] = i as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{game, snake};
+
+    /// The head closes a 2x2 pocket in the corner, the top of the pocket is `top`
+    fn pocket(top: impl IntoIterator<Item = (i32, i32)>) -> Vec<(i32, i32)> {
+        [(2, 1), (2, 0), (3, 0), (3, 1), (3, 2), (2, 2)]
+            .into_iter()
+            .chain(top)
+            .collect()
+    }
+
+    #[test]
+    fn open_board_follows_the_own_tail() {
+        let state = State::from(&game(vec![snake("you", [(5, 5), (5, 4), (5, 3)], 100)]));
+        let survival = state.survival(0);
+
+        assert!(survival.safe_loop && survival.own_tail);
+        assert_eq!(survival.turns, u16::MAX);
+        assert_eq!(survival.score(3), 1.0);
+    }
+
+    #[test]
+    fn closed_pocket_counts_its_cells() {
+        let body = pocket([(1, 2), (0, 2)].into_iter().chain((3..10).map(|y| (0, y))));
+        let state = State::from(&game(vec![snake("you", body, 100)]));
+        let survival = state.survival(0);
+
+        assert!(!survival.safe_loop);
+        assert_eq!(survival.turns, 4);
+        assert_eq!(survival.score(15), 4.0 / 15.0);
+    }
+
+    #[test]
+    fn dead_end_branches_do_not_count() {
+        // below the head the snake can turn left or right, but not fill both sides
+        let body = [
+            (1, 1),
+            (0, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (2, 1),
+            (3, 1),
+            (3, 0),
+        ]
+        .into_iter()
+        .chain((4..10).map(|x| (x, 0)));
+        let state = State::from(&game(vec![snake("you", body, 100)]));
+        let survival = state.survival(0);
+
+        assert!(!survival.safe_loop);
+        assert_eq!(survival.turns, 2);
+        assert_eq!(survival.score(14), 2.0 / 14.0);
+    }
+
+    #[test]
+    fn loop_behind_an_enemy_counts_half() {
+        // the pocket is closed by the tail of an enemy
+        let you = snake("you", pocket((3..10).map(|y| (2, y))), 100);
+        let enemy = snake("enemy", [(0, 5), (0, 4), (0, 3), (0, 2), (1, 2)], 100);
+        let state = State::from(&game(vec![you, enemy]));
+        let survival = state.survival(0);
+
+        assert!(survival.safe_loop && !survival.own_tail);
+        assert_eq!(survival.turns, 4);
+        assert_eq!(survival.score(13), 0.5 + 0.5 * 4.0 / 13.0);
+    }
+}
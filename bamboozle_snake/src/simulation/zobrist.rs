@@ -0,0 +1,302 @@
+use crate::game::Coord;
+use crate::simulation::{RoyaleShrink, Snake, State};
+
+const SNAKE_CELL: u64 = 1;
+const SNAKE_HEAD: u64 = 2;
+const SNAKE_TAIL: u64 = 3;
+const SNAKE_LENGTH: u64 = 4;
+const SNAKE_HEALTH: u64 = 5;
+const FOOD: u64 = 6;
+const HAZARD: u64 = 7;
+const ROYALE: u64 = 8;
+const PHASE: u64 = 9;
+
+/// Health is hashed in buckets, so positions that only differ slightly in health match
+const HEALTH_BUCKET: i16 = 10;
+
+/// Random key of a feature, generated on demand so boards of any size are supported
+#[inline]
+fn key(feature: u64, a: u64, b: u64) -> u64 {
+    // splitmix64
+    let mut z = (feature << 56 ^ a << 32 ^ b).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl State {
+    fn cell_index(&self, pos: Coord) -> u64 {
+        let x = pos.x.rem_euclid(self.grid.width as i32) as u64;
+        let y = pos.y.rem_euclid(self.grid.height as i32) as u64;
+        y * self.grid.width as u64 + x
+    }
+
+    fn cell_key(&self, snake_i: usize, pos: Coord) -> u64 {
+        key(SNAKE_CELL, snake_i as u64, self.cell_index(pos))
+    }
+
+    fn head_key(&self, snake_i: usize, pos: Coord) -> u64 {
+        key(SNAKE_HEAD, snake_i as u64, self.cell_index(pos))
+    }
+
+    fn tail_key(&self, snake_i: usize, pos: Coord) -> u64 {
+        key(SNAKE_TAIL, snake_i as u64, self.cell_index(pos))
+    }
+
+    fn length_key(snake_i: usize, length: usize) -> u64 {
+        key(SNAKE_LENGTH, snake_i as u64, length as u64)
+    }
+
+    fn health_key(snake_i: usize, health: i16) -> u64 {
+        key(
+            SNAKE_HEALTH,
+            snake_i as u64,
+            (health / HEALTH_BUCKET).max(0) as u64,
+        )
+    }
+
+    pub(crate) fn food_key(&self, pos: Coord) -> u64 {
+        key(FOOD, 0, self.cell_index(pos))
+    }
+
+    /// Key of a cell covered by `count` stacked hazards (zero for no hazard)
+    fn hazard_key(&self, pos: Coord, count: u8) -> u64 {
+        if count == 0 {
+            0
+        } else {
+            key(HAZARD, count as u64, self.cell_index(pos))
+        }
+    }
+
+    pub(crate) fn royale_key(royale: &Option<RoyaleShrink>) -> u64 {
+        royale.map_or(0, |r| {
+            let area = [r.min_x, r.max_x, r.min_y, r.max_y];
+            let possible = r.possible;
+
+            area.iter()
+                .chain(possible.iter())
+                .enumerate()
+                .fold(0, |acc, (i, v)| acc ^ key(ROYALE, i as u64, *v as u64))
+        })
+    }
+
+    /// Key of the turn within the hazard schedule of the map, the same board is a different
+    /// position one turn before the next hazards are due
+    pub(crate) fn phase_key(&self) -> u64 {
+        let period = self.config.map.schedule_period(&self.config.settings);
+
+        if period == 0 {
+            0
+        } else {
+            key(PHASE, 0, (self.turn % period) as u64)
+        }
+    }
+
+    /// Hash of an alive snake
+    fn snake_hash(&self, snake_i: usize, snake: &Snake) -> u64 {
+        let body = snake
+            .body()
+            .fold(0, |acc, pos| acc ^ self.cell_key(snake_i, *pos));
+
+        body ^ self.head_key(snake_i, snake.head())
+            ^ self.tail_key(snake_i, *snake.tail())
+            ^ State::length_key(snake_i, snake.len())
+            ^ State::health_key(snake_i, snake.health)
+    }
+
+    /// Change of the hash of a snake that moved from `old` to `new`, `popped` is false
+    /// if the old tail stayed in place (constrictor)
+    pub(crate) fn snake_hash_delta(
+        &self,
+        snake_i: usize,
+        old: &SnakeKey,
+        new: &Snake,
+        popped: bool,
+    ) -> u64 {
+        let mut delta = self.cell_key(snake_i, new.head());
+
+        let kept_len = if popped {
+            delta ^= self.cell_key(snake_i, old.tail);
+            old.len - 1
+        } else {
+            old.len
+        };
+        // tails pushed by eating or shared length
+        for _ in kept_len + 1..new.len() {
+            delta ^= self.cell_key(snake_i, *new.tail());
+        }
+
+        delta ^= self.head_key(snake_i, old.head) ^ self.head_key(snake_i, new.head());
+        delta ^= self.tail_key(snake_i, old.tail) ^ self.tail_key(snake_i, *new.tail());
+        delta ^= State::length_key(snake_i, old.len) ^ State::length_key(snake_i, new.len());
+        delta ^= State::health_key(snake_i, old.health) ^ State::health_key(snake_i, new.health);
+
+        // eliminated snakes are removed from the hash
+        if !new.is_alive() {
+            delta ^= self.snake_hash(snake_i, new);
+        }
+        delta
+    }
+
+    /// Zobrist hash of the state computed from scratch.
+    /// Only the phase of the turn in the hazard schedule is hashed, so repeated positions
+    /// match as long as the next hazards are due at the same time. `food_chance` is left out,
+    /// it only weights the expected food of otherwise equal boards.
+    #[must_use]
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (i, snake) in self.snakes.iter().enumerate().filter(|(_, s)| s.is_alive()) {
+            hash ^= self.snake_hash(i, snake);
+        }
+
+        hash ^ self.items_hash() ^ State::royale_key(&self.royale) ^ self.phase_key()
+    }
+
+    /// Hash of the food and the hazards on the grid
+    pub(crate) fn items_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for pos in self.food.iter() {
+            hash ^= self.food_key(*pos);
+        }
+        for (i, cell) in self.grid.cells.iter().enumerate() {
+            let pos = Coord {
+                x: (i % self.grid.width) as i32,
+                y: (i / self.grid.width) as i32,
+            };
+            hash ^= self.hazard_key(pos, cell.hazard);
+        }
+        hash
+    }
+
+    /// Stack another hazard on the cell
+    pub fn add_hazard(&mut self, pos: Coord) {
+        let count = self.grid[pos].hazard;
+        self.hash ^= self.hazard_key(pos, count) ^ self.hazard_key(pos, count + 1);
+
+        self.grid[pos].hazard += 1;
+        self.hazards.push(pos);
+    }
+}
+
+/// Values of a snake before a step that are needed to update the hash
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SnakeKey {
+    pub head: Coord,
+    pub tail: Coord,
+    pub len: usize,
+    pub health: i16,
+}
+
+impl From<&Snake> for SnakeKey {
+    fn from(snake: &Snake) -> Self {
+        Self {
+            head: snake.head(),
+            tail: *snake.tail(),
+            len: snake.len(),
+            health: snake.health,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Direction;
+    use crate::simulation::{FoodSpawn, State};
+    use crate::test_util::{load_games, random_actions, with_rules, RULESETS};
+
+    #[test]
+    fn initial_hash_matches_recomputation() {
+        for game in load_games() {
+            let state = State::from(&game);
+            assert_eq!(state.hash, state.compute_hash());
+            assert_ne!(state.hash, 0);
+        }
+    }
+
+    #[test]
+    fn step_hash_matches_recomputation() {
+        let mut rng = rand::thread_rng();
+
+        for game in load_games() {
+            for (ruleset, map) in RULESETS {
+                for food_spawn in [FoodSpawn::Expected, FoodSpawn::Sampled] {
+                    for _ in 0..20 {
+                        let mut state = State::from(&with_rules(&game, ruleset, map));
+                        state.food_spawn = food_spawn;
+
+                        while !state.is_end_state() && state.turn < game.turn + 100 {
+                            let actions = random_actions(&state, &mut rng);
+                            state = state.step(&actions);
+
+                            assert_eq!(
+                                state.hash,
+                                state.compute_hash(),
+                                "{ruleset} {map} turn {}",
+                                state.turn
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn apply_and_undo_keep_hash() {
+        let mut rng = rand::thread_rng();
+
+        for game in load_games() {
+            for (ruleset, map) in RULESETS {
+                for _ in 0..20 {
+                    let mut state = State::from(&with_rules(&game, ruleset, map));
+
+                    while !state.is_end_state() && state.turn < game.turn + 100 {
+                        let actions = random_actions(&state, &mut rng);
+                        let old_hash = state.hash;
+                        let stepped = state.step(&actions);
+
+                        let delta = state.apply(&actions);
+                        assert_eq!(state.hash, stepped.hash, "{ruleset} {map}");
+                        assert_eq!(state.hash, state.compute_hash(), "{ruleset} {map}");
+
+                        state.undo(delta);
+                        assert_eq!(state.hash, old_hash, "{ruleset} {map}");
+
+                        state = stepped;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hash_identifies_positions() {
+        let game = &load_games()[0];
+        let state = State::from(game);
+        let actions: Vec<Direction> = (0..state.snakes.len())
+            .map(|i| state.get_valid_actions(i)[0])
+            .collect();
+
+        assert_eq!(state.step(&actions).hash, state.clone().step(&actions).hash);
+        assert_ne!(state.step(&actions).hash, state.hash);
+    }
+
+    #[test]
+    fn hash_includes_the_schedule_phase() {
+        let game = &load_games()[0];
+
+        // royale shrinks every 2 turns in the tests, the spiral grows every 3
+        for (map, period) in [("royale", 2), ("hz_spiral", 3), ("standard", 0)] {
+            let mut state = State::from(&with_rules(game, "standard", map));
+            let hash = state.compute_hash();
+
+            state.turn += 1;
+            assert_eq!(state.compute_hash() == hash, period == 0, "{map}");
+
+            state.turn += period.max(1) - 1;
+            assert_eq!(state.compute_hash(), hash, "{map}");
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::game::{Battlesnake, Coord, Direction, GameState};
+use crate::simulation::State;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+pub const RULESETS: [(&str, &str); 8] = [
+    ("standard", "standard"),
+    ("royale", "standard"),
+    ("wrapped", "standard"),
+    ("constrictor", "standard"),
+    ("standard", "hz_spiral"),
+    ("standard", "snail_mode"),
+    ("squad", "standard"),
+    ("solo", "standard"),
+];
+
+pub fn load_games() -> Vec<GameState> {
+    let file = std::fs::File::open("games.json").expect("games.json is missing");
+    serde_json::from_reader(file).expect("Could not parse games")
+}
+
+pub fn with_rules(game: &GameState, ruleset: &str, map: &str) -> GameState {
+    let mut game = game.clone();
+    game.game.ruleset.name = ruleset.to_string();
+    game.game.map = map.to_string();
+    game.game.ruleset.settings.royale.shrink_every_n_turns = 2;
+
+    match (ruleset, map) {
+        ("constrictor", _) => {
+            // constrictor snakes always have a stacked tail
+            for snake in game.board.snakes.iter_mut().chain([&mut game.you]) {
+                let tail = *snake.body.last().unwrap();
+                snake.body.push(tail);
+            }
+        }
+        ("squad", _) => {
+            game.you.squad = "a".to_string();
+            for (i, snake) in game.board.snakes.iter_mut().enumerate() {
+                snake.squad = if i < 2 { "a" } else { "b" }.to_string();
+            }
+        }
+        ("solo", _) => game.board.snakes.retain(|s| s.id == game.you.id),
+        (_, "hz_spiral") => game.board.hazards = vec![Coord { x: 5, y: 5 }],
+        _ => (),
+    }
+    game
+}
+
+/// Snake of the first test game moved to `body`, head first
+pub fn snake(id: &str, body: impl IntoIterator<Item = (i32, i32)>, health: u32) -> Battlesnake {
+    let mut snake = load_games()[0].you.clone();
+    snake.id = id.to_string();
+    snake.body = body.into_iter().map(|(x, y)| Coord { x, y }).collect();
+    snake.head = snake.body[0];
+    snake.health = health;
+    snake
+}
+
+/// First test game with only these snakes and no food or hazards, the first snake is you
+pub fn game(snakes: Vec<Battlesnake>) -> GameState {
+    let mut game = load_games()[0].clone();
+    game.you = snakes[0].clone();
+    game.board.snakes = snakes;
+    game.board.food.clear();
+    game.board.hazards.clear();
+    game
+}
+
+pub fn random_actions(state: &State, rng: &mut impl Rng) -> Vec<Direction> {
+    (0..state.snakes.len())
+        .map(|i| {
+            // sometimes walk into walls and bodies
+            if rng.gen_bool(0.1) {
+                *Direction::get_alive_actions().choose(rng).unwrap()
+            } else {
+                *state.get_valid_actions(i).choose(rng).unwrap()
+            }
+        })
+        .collect()
+}
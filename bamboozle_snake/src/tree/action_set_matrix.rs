@@ -1,19 +1,19 @@
 use crate::game::Direction;
+use crate::simulation::{ActionSet, JointActions};
 use crate::tree::Node;
-use itertools::Itertools;
 use std::ops::{Index, IndexMut};
 
 #[derive(Debug, Clone)]
 pub struct ActionSetMatrix {
     pub sets: Vec<Option<Node>>,
-    pub action_sets: Vec<Vec<Direction>>,
+    pub action_sets: Vec<ActionSet>,
     pub num_snakes: u32,
 }
 
-impl Index<&Vec<Direction>> for ActionSetMatrix {
+impl Index<&[Direction]> for ActionSetMatrix {
     type Output = Option<Node>;
 
-    fn index(&self, action_set: &Vec<Direction>) -> &Self::Output {
+    fn index(&self, action_set: &[Direction]) -> &Self::Output {
         let mut index: usize = 0;
 
         for (i, action) in action_set.iter().enumerate() {
@@ -23,8 +23,8 @@ impl Index<&Vec<Direction>> for ActionSetMatrix {
     }
 }
 
-impl IndexMut<&Vec<Direction>> for ActionSetMatrix {
-    fn index_mut(&mut self, action_set: &Vec<Direction>) -> &mut Self::Output {
+impl IndexMut<&[Direction]> for ActionSetMatrix {
+    fn index_mut(&mut self, action_set: &[Direction]) -> &mut Self::Output {
         let mut index: usize = 0;
 
         for (i, action) in action_set.iter().enumerate() {
@@ -43,8 +43,8 @@ impl ActionSetMatrix {
         }
     }
 
-    pub fn fill(&mut self, node: &Node, valid_actions: Vec<Vec<Direction>>) {
-        for action_set in valid_actions.into_iter().multi_cartesian_product() {
+    pub fn fill(&mut self, node: &Node, joint_actions: JointActions) {
+        for action_set in joint_actions {
             self[&action_set] = Option::Some(node.step(&action_set));
             self.action_sets.push(action_set);
         }
@@ -66,9 +66,8 @@ impl ActionSetMatrix {
             let index = base_index + (i * dim_step);
 
             for s in 0..steps_in_dim {
-                match &self.sets[(index + s) as usize] {
-                    Some(node) => nodes.push(node),
-                    None => (),
+                if let Some(node) = &self.sets[(index + s) as usize] {
+                    nodes.push(node);
                 }
             }
         }
@@ -1,8 +1,7 @@
 use crate::game::Direction;
 use crate::heuristic::Heuristic;
-use crate::simulation::{LossType, Outcome};
+use crate::simulation::{ActionSet, LossType, Outcome};
 use crate::tree::{get_best_action, Node};
-use itertools::Itertools;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -24,11 +23,56 @@ impl AlphaBeta {
     }
 }
 
+/// Longest principal line that is recorded, deeper joint actions are dropped
+const MAX_LINE: usize = 32;
+
+/// Joint actions from a node to the leaf its outcome comes from, stored inline
+#[derive(Debug, Clone, Copy)]
+pub struct Line {
+    sets: [ActionSet; MAX_LINE],
+    len: usize,
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self {
+            sets: [ActionSet::EMPTY; MAX_LINE],
+            len: 0,
+        }
+    }
+}
+
+impl Line {
+    /// Line of a child node prefixed with the joint action that leads to it
+    pub fn set(&mut self, action_set: ActionSet, child: &Line) {
+        let len = child.len.min(MAX_LINE - 1);
+        self.sets[0] = action_set;
+        self.sets[1..=len].copy_from_slice(&child.sets[..len]);
+        self.len = len + 1;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ActionSet> {
+        self.sets[..self.len].iter()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Search the root node, `line` receives the principal line if given
 pub fn run_alphabeta(
-    root_node: &Node,
+    root_node: &mut Node,
     heuristic: Arc<dyn Heuristic>,
     max_depth: u32,
     should_abort: Arc<AtomicBool>,
+    line: Option<&mut Line>,
 ) -> (Direction, Outcome, usize) {
     let alpha_beta = AlphaBeta::new(f32::MIN, f32::MAX);
 
@@ -38,31 +82,44 @@ pub fn run_alphabeta(
         heuristic.as_ref(),
         alpha_beta,
         should_abort,
+        line,
     )
 }
 
+/// Paranoid alpha-beta search of the node. The principal line is only recorded if `line`
+/// is given, it is empty for leaves
 pub fn eval_node(
-    node: &Node,
+    node: &mut Node,
     max_depth: u32,
     heuristic: &dyn Heuristic,
     alpha_beta: AlphaBeta,
     should_abort: Arc<AtomicBool>,
+    mut line: Option<&mut Line>,
 ) -> (Direction, Outcome, usize) {
+    if let Some(line) = line.as_deref_mut() {
+        line.len = 0;
+    }
+
     // ============ termination conditions ============
     if should_abort.load(Ordering::Relaxed) || alpha_beta.should_abort() {
         return (Direction::None, Outcome::Loss(LossType::default()), 1);
     }
     if node.state.is_end_state() {
         return match node.state.get_winner() {
+            -1 if node.state.config.solo => (
+                Direction::None,
+                Outcome::Loss(node.state.snakes[0].loss_reason()),
+                1,
+            ),
             -1 => (Direction::None, Outcome::Draw, 1),
-            0 => (
+            winner if node.state.is_our_team(winner as usize) => (
                 Direction::None,
                 Outcome::Win(-(node.state.snakes[0].len() as f32)),
                 1,
             ),
             _ => (
                 Direction::None,
-                Outcome::Loss(node.state.snakes[0].loss_reason),
+                Outcome::Loss(node.state.snakes[0].loss_reason()),
                 1,
             ),
         };
@@ -71,7 +128,7 @@ pub fn eval_node(
     if !node.state.snakes[0].is_alive() {
         return (
             Direction::None,
-            Outcome::Loss(node.state.snakes[0].loss_reason),
+            Outcome::Loss(node.state.snakes[0].loss_reason()),
             1,
         );
     }
@@ -81,61 +138,81 @@ pub fn eval_node(
     }
 
     // ============ recursive evaluation ============
-    let num_snakes = node.state.snakes.len();
     let mut evaluated_nodes = 1;
 
-    // valid actions of snakes (our actions are empty for now)
-    let mut valid_actions_blueprint: Vec<Vec<Direction>> = Vec::with_capacity(num_snakes);
-    valid_actions_blueprint.push(Vec::new());
-    valid_actions_blueprint.extend((1..num_snakes).map(|si| node.state.get_valid_actions(si)));
+    let (mut own_actions, own_len) = node.state.valid_moves(0).to_array();
+    let joint_actions = node.state.joint_actions();
+
+    // try the safest moves first at the root
+    if node.depth == 0 {
+        let classes = node.state.classify_moves(0);
+        own_actions[..own_len].sort_by_key(|a| -classes[*a as usize].score());
+    }
 
     let mut alpha_beta = alpha_beta;
 
     // ============ max step ============
-    let mut scores = [Outcome::Loss(LossType::OwnOrWallCollision); 4];
+    let mut scores = [Outcome::Loss(LossType::WallCollision); 4];
+    // principal lines of the child, of the worst reply and of every own action
+    let mut child_line = line.is_some().then(Line::default);
+    let mut min_line = line.is_some().then(Line::default);
+    let mut lines = line.is_some().then(|| [Line::default(); 4]);
 
-    for own_action in node.state.get_valid_actions(0).into_iter() {
+    for own_action in own_actions.into_iter().take(own_len) {
         if alpha_beta.should_abort() {
             break;
         }
-        let mut valid_actions = valid_actions_blueprint.clone();
-        valid_actions[0].push(own_action);
 
         // ============ min step ============
         let mut worst_outcome = Outcome::Win(1000.0);
         let mut alpha_beta_min = alpha_beta;
-        for action_set in valid_actions.into_iter().multi_cartesian_product() {
+        if let Some(min_line) = &mut min_line {
+            min_line.len = 0;
+        }
+        for action_set in joint_actions.with_action(0, own_action) {
             if alpha_beta_min.should_abort() {
                 worst_outcome = Outcome::Loss(LossType::default());
                 break;
             }
 
-            // simulate actions
-            let next_node = node.step(&action_set);
+            // simulate actions in place
+            let delta = node.apply(&action_set);
 
             let (_, outcome, ev_nodes) = eval_node(
-                &next_node,
+                node,
                 max_depth,
                 heuristic,
                 alpha_beta_min,
                 should_abort.clone(),
+                child_line.as_mut(),
             );
 
+            node.undo(delta);
+
             evaluated_nodes += ev_nodes;
 
             if outcome < worst_outcome {
                 worst_outcome = outcome;
+                if let (Some(min_line), Some(child_line)) = (&mut min_line, &child_line) {
+                    min_line.set(action_set, child_line);
+                }
             }
             if alpha_beta_min.beta > worst_outcome.get_score() {
                 alpha_beta_min.beta = worst_outcome.get_score();
             }
 
-            // TODO: check LossType
-            if worst_outcome == Outcome::Loss(LossType::OwnOrWallCollision) {
+            // our own collisions do not depend on the actions of the enemies
+            if matches!(
+                worst_outcome,
+                Outcome::Loss(LossType::WallCollision | LossType::SelfCollision)
+            ) {
                 break;
             }
         }
         scores[own_action as usize] = worst_outcome;
+        if let (Some(lines), Some(min_line)) = (&mut lines, &min_line) {
+            lines[own_action as usize] = *min_line;
+        }
 
         if alpha_beta.alpha < worst_outcome.get_score() {
             alpha_beta.alpha = worst_outcome.get_score();
@@ -143,6 +220,53 @@ pub fn eval_node(
     }
 
     let (dir, best_outcome) = get_best_action(scores);
+    if let (Some(line), Some(lines)) = (line, &lines) {
+        if dir != Direction::None {
+            *line = lines[dir as usize];
+        }
+    }
 
     (dir, best_outcome, evaluated_nodes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic::StandardHeuristic;
+    use crate::simulation::State;
+    use crate::test_util::load_games;
+
+    #[test]
+    fn principal_line_leads_to_the_leaf_of_the_outcome() {
+        let heuristic = StandardHeuristic::default();
+        let mut heuristic_leaves = 0;
+
+        for game in load_games() {
+            let state = State::from(&game);
+            let mut node = Node::new(state.clone(), 0);
+            let mut line = Line::default();
+
+            let (dir, outcome, _) = run_alphabeta(
+                &mut node,
+                Arc::new(heuristic),
+                2,
+                Arc::new(AtomicBool::new(false)),
+                Some(&mut line),
+            );
+            assert_eq!(node.state.hash, state.hash);
+
+            let mut leaf = state.clone();
+            for action_set in line.iter() {
+                leaf = leaf.step(action_set);
+            }
+
+            assert_eq!(line.iter().next().map(|set| set[0]), Some(dir));
+            if let Outcome::Heuristic(_) = outcome {
+                assert_eq!(line.len(), 2);
+                assert_eq!(heuristic.eval(&leaf), outcome);
+                heuristic_leaves += 1;
+            }
+        }
+        assert!(heuristic_leaves > 0);
+    }
+}
@@ -42,12 +42,12 @@ fn eval_node(
         return match node.state.get_winner() {
             -1 => (Direction::None, Outcome::Draw, 1),
             0 => (Direction::None, Outcome::Win, 1),
-            _ => (Direction::None, Outcome::Loss(node.state.snakes[0].loss_reason), 1),
+            _ => (Direction::None, Outcome::Loss(node.state.snakes[0].loss_reason()), 1),
         };
     }
 
     if !node.state.snakes[0].is_alive() {
-        return (Direction::None, Outcome::Loss(node.state.snakes[0].loss_reason), 1);
+        return (Direction::None, Outcome::Loss(node.state.snakes[0].loss_reason()), 1);
     }
 
     if node.depth == max_depth {
@@ -66,7 +66,7 @@ fn eval_node(
     let mut alpha_beta = alpha_beta;
 
     // ============ max step ============
-    let mut scores = [Outcome::Loss(LossType::OwnOrWallCollision); 4];
+    let mut scores = [Outcome::Loss(LossType::WallCollision); 4];
 
     let results: Vec<Outcome> = node
         .state
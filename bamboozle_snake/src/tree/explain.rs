@@ -0,0 +1,244 @@
+use crate::game::Direction;
+use crate::heuristic::{Floodfill, Heuristic};
+use crate::simulation::{LossType, Outcome, State};
+use crate::tree::alphabeta::{eval_node, AlphaBeta, Line};
+use crate::tree::{get_best_action, Node};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::iter::zip;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// Weighted contribution of a heuristic term to the score
+#[derive(Debug, Clone, Serialize)]
+pub struct Term {
+    pub name: &'static str,
+    pub value: f32,
+    pub weight: f32,
+    pub score: f32,
+}
+
+/// Cells a snake reaches first in the floodfill
+#[derive(Debug, Clone, Serialize)]
+pub struct Ownership {
+    pub snake: usize,
+    pub cells: usize,
+    pub snake_cells: usize,
+    pub dead_end: bool,
+}
+
+/// Principal leaf of a root move
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveExplanation {
+    pub action: Direction,
+    pub outcome: Outcome,
+    pub leaf_depth: u32,
+    /// joint actions from the root to the leaf
+    pub line: Vec<Vec<Direction>>,
+    /// empty if the game is decided at the leaf
+    pub terms: Vec<Term>,
+    pub ownership: Vec<Ownership>,
+}
+
+/// Score breakdown of every root move of a search
+#[derive(Debug, Clone, Serialize)]
+pub struct Explanation {
+    pub heuristic: String,
+    pub depth: u32,
+    pub best: Direction,
+    pub moves: Vec<MoveExplanation>,
+}
+
+/// Search every root move to `depth` and explain the leaf its outcome comes from
+#[must_use]
+pub fn explain(state: &State, heuristic: &dyn Heuristic, depth: u32) -> Explanation {
+    let depth = depth.max(1);
+
+    let mut root_node = Node::new(state.clone(), 0);
+    root_node.update_snake_simulation(depth);
+
+    let (own_actions, own_len) = state.valid_moves(0).to_array();
+    let mut scores = [Outcome::Loss(LossType::WallCollision); 4];
+
+    let moves = own_actions[..own_len]
+        .iter()
+        .map(|action| {
+            let explanation = explain_move(&mut root_node, *action, heuristic, depth);
+            scores[*action as usize] = explanation.outcome;
+            explanation
+        })
+        .collect();
+
+    Explanation {
+        heuristic: format!("{:?}", heuristic),
+        depth,
+        best: get_best_action(scores).0,
+        moves,
+    }
+}
+
+/// Explain the principal line a search to `depth` found for its best move
+#[must_use]
+pub fn explain_line(
+    state: &State,
+    heuristic: &dyn Heuristic,
+    depth: u32,
+    outcome: Outcome,
+    line: &Line,
+) -> Explanation {
+    let mut root_node = Node::new(state.clone(), 0);
+    root_node.update_snake_simulation(depth);
+
+    let best = line.iter().next().map_or(Direction::None, |set| set[0]);
+
+    Explanation {
+        heuristic: format!("{:?}", heuristic),
+        depth,
+        best,
+        moves: vec![explain_leaf(root_node, best, outcome, line, heuristic)],
+    }
+}
+
+/// Min step of the root with a full window, so every move gets its exact outcome
+fn explain_move(
+    root_node: &mut Node,
+    action: Direction,
+    heuristic: &dyn Heuristic,
+    max_depth: u32,
+) -> MoveExplanation {
+    let should_abort = Arc::new(AtomicBool::new(false));
+    let full_window = AlphaBeta::new(f32::MIN, f32::MAX);
+
+    let mut child_line = Line::default();
+    let mut worst: Option<(Outcome, Line)> = None;
+
+    for action_set in root_node.state.joint_actions().with_action(0, action) {
+        let delta = root_node.apply(&action_set);
+        let (_, outcome, _) = eval_node(
+            root_node,
+            max_depth,
+            heuristic,
+            full_window,
+            should_abort.clone(),
+            Some(&mut child_line),
+        );
+        root_node.undo(delta);
+
+        if worst.is_none_or(|(w, _)| outcome < w) {
+            let mut line = Line::default();
+            line.set(action_set, &child_line);
+            worst = Some((outcome, line));
+        }
+        // same shortcut as the search
+        if matches!(
+            outcome,
+            Outcome::Loss(LossType::WallCollision | LossType::SelfCollision)
+        ) {
+            break;
+        }
+    }
+
+    let (outcome, line) = worst.unwrap_or((Outcome::Loss(LossType::default()), Line::default()));
+    explain_leaf(root_node.clone(), action, outcome, &line, heuristic)
+}
+
+/// Follow the line from the root and break down the heuristic at its end
+fn explain_leaf(
+    mut node: Node,
+    action: Direction,
+    outcome: Outcome,
+    line: &Line,
+    heuristic: &dyn Heuristic,
+) -> MoveExplanation {
+    for action_set in line.iter() {
+        node.apply(action_set);
+    }
+
+    let leaf = &node.state;
+    let terms = match heuristic.features(leaf).swap_remove(0) {
+        Some(features) if !leaf.is_end_state() => {
+            zip(features.iter(), heuristic.weights().values())
+                .map(|((name, value), &weight)| Term {
+                    name,
+                    value,
+                    weight,
+                    score: weight * value,
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let floodfill = Floodfill::new(leaf, heuristic.flood_type(leaf));
+    let ownership = (0..leaf.snakes.len())
+        .filter(|i| leaf.snakes[*i].is_alive())
+        .map(|i| {
+            let (cells, snake_cells) = floodfill.count_owned(i as u8);
+            Ownership {
+                snake: i,
+                cells,
+                snake_cells,
+                dead_end: floodfill.dead_ends[i],
+            }
+        })
+        .collect();
+
+    MoveExplanation {
+        action,
+        outcome,
+        leaf_depth: node.depth,
+        line: line.iter().map(|set| set.to_vec()).collect(),
+        terms,
+        ownership,
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Explanation of {:?} at depth {} with {}",
+            self.best, self.depth, self.heuristic
+        )?;
+
+        for m in self.moves.iter() {
+            writeln!(
+                f,
+                "  {:?}: {:?} at leaf depth {}",
+                m.action, m.outcome, m.leaf_depth
+            )?;
+            let line = m
+                .line
+                .iter()
+                .map(|set| format!("{:?}", set))
+                .collect::<Vec<_>>();
+            writeln!(f, "    line: {}", line.join(" "))?;
+
+            for term in m.terms.iter() {
+                writeln!(
+                    f,
+                    "    {:<14} {:>9.4} x {:>6.2} = {:>9.4}",
+                    term.name, term.value, term.weight, term.score
+                )?;
+            }
+            if !m.terms.is_empty() {
+                let total: f32 = m.terms.iter().map(|t| t.score).sum();
+                writeln!(f, "    {:<14} {:>30.4}", "total", total)?;
+            }
+
+            let ownership = m
+                .ownership
+                .iter()
+                .map(|o| {
+                    let dead_end = if o.dead_end { ", dead end" } else { "" };
+                    format!(
+                        "#{} {} (+{} snake){}",
+                        o.snake, o.cells, o.snake_cells, dead_end
+                    )
+                })
+                .collect::<Vec<_>>();
+            writeln!(f, "    ownership: {}", ownership.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use crate::game::{Direction, GameState};
 use crate::heuristic::Heuristic;
 use crate::simulation::{LossType, Outcome, State};
-use crate::tree::{alphabeta, Node, TreeAlgorithm};
-use log::debug;
+use crate::tree::{alphabeta, explain_line, Line, Node, TreeAlgorithm};
+use log::{debug, info};
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
@@ -11,15 +11,31 @@ use std::time::{Duration, Instant};
 
 use crate::logic::CONFIG;
 
+/// The only move that survives the next turn, or the safest move if none does
+fn forced_move(state: &State) -> Option<Direction> {
+    let classes = state.classify_moves(0);
+    debug!("move classes {:?}", classes);
+
+    let mut alive = Direction::get_alive_actions()
+        .into_iter()
+        .filter(|dir| !classes[*dir as usize].is_death());
+
+    match (alive.next(), alive.next()) {
+        (None, _) => Some(state.safest_move(0)),
+        (Some(action), None) => Some(action),
+        _ => None,
+    }
+}
+
 pub fn iterative_search_mt(game_state: GameState, heuristic: Arc<dyn Heuristic>) -> Direction {
     let start_time = Instant::now();
     let available_time = Duration::from_millis(CONFIG.timeout);
 
     let state = State::from(&game_state);
 
-    // return if only one action is available
-    if state.get_valid_actions(0).len() == 1 {
-        return state.get_valid_actions(0)[0];
+    // return if only one action does not lead to certain death
+    if let Some(action) = forced_move(&state) {
+        return action;
     }
     let root_node = Node::new(state.clone(), 0);
 
@@ -46,13 +62,14 @@ pub fn iterative_search_mt(game_state: GameState, heuristic: Arc<dyn Heuristic>)
     }
 
     let mut best_action = Direction::None;
-    let mut current_outcome = Outcome::Loss(LossType::OwnOrWallCollision);
+    let mut current_outcome = Outcome::Loss(LossType::WallCollision);
     let mut current_depth = 0;
+    let mut current_line = None;
     let mut remaining_time = available_time - start_time.elapsed();
     // let mut death_reasons = [(Outcome::Loss(LossType::None), 0); 4];
 
     // calc best action with respect to timeout
-    while let Ok((depth, action, outcome)) = receiver.recv_timeout(remaining_time) {
+    while let Ok((depth, action, outcome, line)) = receiver.recv_timeout(remaining_time) {
         debug!(
             "{:?} ({:?}) after a depth of {} in {:?} ",
             outcome,
@@ -65,17 +82,19 @@ pub fn iterative_search_mt(game_state: GameState, heuristic: Arc<dyn Heuristic>)
         //     death_reasons[action as usize] = (outcome, depth);
         // }
 
-        if depth > current_depth && outcome > Outcome::Loss(LossType::None) {
+        if depth > current_depth && !matches!(outcome, Outcome::Loss(_)) {
             current_depth = depth;
             best_action = action;
             current_outcome = outcome;
+            current_line = line;
             if matches!(outcome, Outcome::Win(_)) {
                 break;
             }
-        } else if current_outcome < Outcome::Loss(LossType::None) && outcome > current_outcome {
+        } else if matches!(current_outcome, Outcome::Loss(_)) && outcome > current_outcome {
             current_depth = depth;
             best_action = action;
             current_outcome = outcome;
+            current_line = line;
         }
 
         remaining_time = available_time.saturating_sub(start_time.elapsed());
@@ -86,7 +105,8 @@ pub fn iterative_search_mt(game_state: GameState, heuristic: Arc<dyn Heuristic>)
     should_abort.store(true, Ordering::SeqCst);
 
     if best_action == Direction::None {
-        best_action = *(state.get_valid_actions(0).first().unwrap_or(&Direction::Up));
+        best_action = state.safest_move(0);
+        current_line = None;
     }
 
     debug!(
@@ -97,12 +117,20 @@ pub fn iterative_search_mt(game_state: GameState, heuristic: Arc<dyn Heuristic>)
         current_outcome,
     );
 
+    // only replays the principal line of the search, cheap enough to not delay the move
+    if let Some(line) = current_line {
+        info!(
+            "{}",
+            explain_line(&state, heuristic.as_ref(), current_depth, current_outcome, &line)
+        );
+    }
+
     best_action
 }
 
 fn iterative_deepening_work(
     work_queue: Arc<Mutex<VecDeque<(u32, Node)>>>,
-    sender: mpsc::Sender<(u32, Direction, Outcome)>,
+    sender: mpsc::Sender<(u32, Direction, Outcome, Option<Line>)>,
     heuristic: Arc<dyn Heuristic>,
     should_abort: Arc<AtomicBool>,
 ) {
@@ -116,10 +144,17 @@ fn iterative_deepening_work(
         drop(queue);
 
         root_node.update_snake_simulation(depth);
-        let (dir, outcome, evaluated_nodes) =
-            alphabeta::run_alphabeta(&root_node, heuristic.clone(), depth, should_abort.clone());
+        // the principal line is only recorded to explain the move
+        let mut line = CONFIG.explain.then(Line::default);
+        let (dir, outcome, evaluated_nodes) = alphabeta::run_alphabeta(
+            &mut root_node,
+            heuristic.clone(),
+            depth,
+            should_abort.clone(),
+            line.as_mut(),
+        );
 
-        if sender.send((depth, dir, outcome)).is_err() {
+        if sender.send((depth, dir, outcome, line)).is_err() {
             break;
         }
     }
@@ -135,8 +170,8 @@ pub fn iterative_search(
 
     let state = State::from(&game_state);
 
-    if state.get_valid_actions(0).len() == 1 {
-        return state.get_valid_actions(0)[0];
+    if let Some(action) = forced_move(&state) {
+        return action;
     }
 
     let root_node = Node::new(state.clone(), 0);
@@ -155,7 +190,10 @@ pub fn iterative_search(
 
     // calc best action with respect to timeout
     while let Ok((action, outcome)) = receiver.recv_timeout(remaining_time) {
-        if outcome == Outcome::Loss(LossType::OwnOrWallCollision) {
+        if matches!(
+            outcome,
+            Outcome::Loss(LossType::WallCollision | LossType::SelfCollision)
+        ) {
             break;
         }
         best_action = action;
@@ -171,7 +209,7 @@ pub fn iterative_search(
     should_abort.store(true, Ordering::SeqCst);
 
     if best_action == Direction::None {
-        best_action = *(state.get_valid_actions(0).first().unwrap_or(&Direction::Up));
+        best_action = state.safest_move(0);
     }
 
     best_action
@@ -190,7 +228,13 @@ fn iterative_deepening(
         let (dir, outcome, evaluated_nodes) = match algorithm {
             TreeAlgorithm::AlphaBeta => {
                 root_node.update_snake_simulation(depth);
-                alphabeta::run_alphabeta(&root_node, heuristic.clone(), depth, should_abort.clone())
+                alphabeta::run_alphabeta(
+                    &mut root_node,
+                    heuristic.clone(),
+                    depth,
+                    should_abort.clone(),
+                    None,
+                )
             }
             TreeAlgorithm::AlphabetaMultithread => {
                 todo!()
@@ -1,8 +1,7 @@
 use crate::game::{Direction, GameState};
 use crate::heuristic::Heuristic;
-use crate::simulation::{Outcome, State};
+use crate::simulation::{ActionSet, Outcome, State};
 use crate::tree::Node;
-use itertools::Itertools;
 use log::debug;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc};
@@ -123,16 +122,7 @@ pub fn eval_node(
 
     // ============ iterative evaluation ============
 
-    // get valid actions for each snake
-    let mut valid_actions: Vec<Vec<Direction>> = Vec::with_capacity(num_snakes);
-    for snake_i in 0..num_snakes {
-        valid_actions.push(node.state.get_valid_actions(snake_i));
-    }
-    let action_sets: Vec<Vec<Direction>> = valid_actions
-        .clone()
-        .into_iter()
-        .multi_cartesian_product()
-        .collect();
+    let action_sets: Vec<ActionSet> = node.state.joint_actions().collect();
 
     // save each outcome
     let mut outcomes = Vec::with_capacity(action_sets.len());
@@ -188,7 +178,7 @@ pub fn eval_node(
 
     let action_means = &outcomes_mean[0];
     for (i, action_set) in action_sets.iter().enumerate() {
-        if action_set[1..] == best_action_set {
+        if action_set[1..] == best_action_set[..] {
             let outcome = outcomes[i][0];
             let mean = action_means[action_set[0] as usize];
 
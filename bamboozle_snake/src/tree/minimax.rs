@@ -1,9 +1,8 @@
 use crate::game::Direction;
 use crate::heuristic::Heuristic;
-use crate::simulation::Outcome;
+use crate::simulation::{JointActions, MoveSet, Outcome};
 use crate::simulation::Outcome::{Draw, Loss, Win};
 use crate::tree::{get_best_action, Node};
-use itertools::Itertools;
 use rayon::prelude::*;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -47,26 +46,23 @@ fn eval_node(
     let num_snakes = node.state.snakes.len();
     let mut scores = [Loss; 4];
 
-    // prepare blueprint for valid actions
-    let mut valid_actions_blueprint: Vec<Vec<Direction>> = Vec::with_capacity(num_snakes);
-    valid_actions_blueprint.push(Vec::new());
-
     let our_snake = &node.state.snakes[0];
 
-    for i in 1..num_snakes {
-        // only fully simulate nearby snakes
-        if num_snakes > 3
+    // only fully simulate nearby snakes
+    let joint_actions = JointActions::new((0..num_snakes).map(|i| {
+        if i > 0
+            && num_snakes > 3
             && node
                 .state
                 .grid
                 .manhattan_dist(&our_snake.head(), &node.state.snakes[i].head())
                 > (max_depth - node.depth) * 2
         {
-            valid_actions_blueprint.push(vec![Direction::None]);
+            MoveSet::NONE
         } else {
-            valid_actions_blueprint.push(node.state.get_valid_actions(i));
+            node.state.valid_moves(i)
         }
-    }
+    }));
 
     // closure for multi-threading
     let eval_action = |(action, outcome): &mut (Direction, Outcome)| {
@@ -74,13 +70,10 @@ fn eval_node(
             return;
         }
 
-        let mut valid_actions = valid_actions_blueprint.clone();
-        valid_actions[0].push(*action);
-
         let mut worst_outcome = Win;
 
         // iterate over possible actions of other snakes
-        for action_set in valid_actions.into_iter().multi_cartesian_product() {
+        for action_set in joint_actions.with_action(0, *action) {
             let action_node = node.step(&action_set);
 
             let (_, outcome, node_count) =
@@ -101,8 +94,8 @@ fn eval_node(
 
     let mut pair_vec: Vec<(Direction, Outcome)> = node
         .state
-        .get_valid_actions(0)
-        .into_iter()
+        .valid_moves(0)
+        .iter()
         .map(|a| (a, Draw))
         .collect();
 
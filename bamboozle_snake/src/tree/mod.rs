@@ -1,5 +1,6 @@
 mod action_set_matrix;
 mod alphabeta;
+mod explain;
 mod iterative_deepening;
 mod node;
 mod monte_carlo;
@@ -7,7 +8,8 @@ mod monte_carlo;
 use crate::game::Direction;
 use crate::simulation::Outcome;
 pub use action_set_matrix::ActionSetMatrix;
-pub use alphabeta::run_alphabeta;
+pub use alphabeta::{run_alphabeta, Line};
+pub use explain::{explain, explain_line, Explanation, MoveExplanation, Ownership, Term};
 pub use iterative_deepening::{iterative_search, iterative_search_mt};
 pub use node::Node;
 pub use monte_carlo::monte_carlo;
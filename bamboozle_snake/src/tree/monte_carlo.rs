@@ -3,10 +3,11 @@ use rand::seq::SliceRandom;
 use tokio::time::Instant;
 
 use crate::game::{Direction, GameState};
-use crate::simulation::{LossType, Outcome, State};
+use crate::simulation::{FoodSpawn, Outcome, State};
 
 pub fn run_to_end(state: &State) -> (Outcome, Direction) {
     let mut state = state.clone();
+    state.food_spawn = FoodSpawn::Sampled;
 
     let mut actions = vec![Direction::None; state.snakes.len()];
 
@@ -25,9 +26,9 @@ pub fn run_to_end(state: &State) -> (Outcome, Direction) {
     }
 
     let outcome = match state.get_winner() {
-        0 => Outcome::Win(0.0),
         -1 => Outcome::Draw,
-        _ => Outcome::Loss(LossType::None)
+        winner if state.is_our_team(winner as usize) => Outcome::Win(0.0),
+        _ => Outcome::Loss(state.snakes[0].loss_reason())
     };
     // debug!("{:?}, {:?}", actions[0], outcome);
     (outcome, action)
@@ -1,5 +1,5 @@
 use crate::game::Direction;
-use crate::simulation::{Mode, State};
+use crate::simulation::{State, StateDelta};
 
 #[derive(Debug, Clone)]
 pub struct Node {
@@ -14,22 +14,34 @@ impl Node {
     }
 
     #[must_use]
-    pub fn step(&self, action_set: &Vec<Direction>) -> Self {
+    pub fn step(&self, action_set: &[Direction]) -> Self {
         let state = self.state.step(action_set);
         let depth = self.depth + 1;
 
         Self::new(state, depth)
     }
 
+    /// Step the node in place, revert it with `undo`
+    pub fn apply(&mut self, action_set: &[Direction]) -> StateDelta {
+        self.depth += 1;
+        self.state.apply(action_set)
+    }
+
+    pub fn undo(&mut self, delta: StateDelta) {
+        self.depth -= 1;
+        self.state.undo(delta);
+    }
+
     pub fn update_snake_simulation(&mut self, max_depth: u32) {
+        let grid = &self.state.grid;
         let snakes = &mut self.state.snakes;
 
         let our_head = snakes[0].head();
 
-        if snakes.len() > 3 && self.state.mode != Mode::Constrictor  {
+        if snakes.len() > 3 && !self.state.config.is_constrictor() {
             for other_snake in snakes.iter_mut().skip(1) {
                 other_snake.should_simulate =
-                    our_head.manhattan_dist(&other_snake.head()) <= 2 * max_depth;
+                    grid.manhattan_dist(&our_head, &other_snake.head()) <= 2 * max_depth;
             }
         }
     }